@@ -0,0 +1,131 @@
+use std::collections::{BTreeMap, HashSet};
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::{Color, Colors, Limit, MissingColor};
+
+/// A named set of bag limits to evaluate the games against.
+#[derive(Deserialize, Debug, PartialEq)]
+pub struct Scenario {
+    pub name: String,
+    pub limits: BTreeMap<String, u32>,
+    #[serde(default)]
+    pub missing: MissingColor,
+}
+
+#[derive(Deserialize)]
+struct Config {
+    scenarios: Vec<Scenario>,
+}
+
+impl Scenario {
+    pub fn limit(&self, colors: &mut Colors) -> Limit {
+        let counts: Vec<(&String, u32)> = self.limits.iter().map(|(k, v)| (k, *v)).collect();
+        return Limit::new(colors, &counts, self.missing);
+    }
+}
+
+/// Parses a limit in the form `red=12,green=13,blue=14`.
+pub fn parse_limit_spec(spec: &str, missing: MissingColor) -> Result<Scenario, String> {
+    let mut limits = BTreeMap::new();
+    for part in spec.split(",") {
+        let (name, count) = part
+            .split_once("=")
+            .ok_or_else(|| format!("expected color=count, got '{part}'"))?;
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(format!("missing color name in '{part}'"));
+        }
+        let count: u32 = count
+            .trim()
+            .parse()
+            .map_err(|err| format!("invalid count in '{part}': {err}"))?;
+        if limits.insert(name.to_string(), count).is_some() {
+            return Err(format!("color '{name}' given more than once in '{spec}'"));
+        }
+    }
+    return Ok(Scenario {
+        name: spec.to_string(),
+        limits,
+        missing,
+    });
+}
+
+/// Loads scenarios from a TOML file, or a JSON file if the extension is `.json`.
+pub fn load_scenarios(path: &Path) -> Result<Vec<Scenario>, String> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|err| format!("failed reading {}: {err}", path.display()))?;
+    let config: Config = if path.extension().is_some_and(|it| it == "json") {
+        serde_json::from_str(&text).map_err(|err| format!("{}: {err}", path.display()))?
+    } else {
+        toml::from_str(&text).map_err(|err| format!("{}: {err}", path.display()))?
+    };
+    return Ok(config.scenarios);
+}
+
+/// Compares the colors named in a scenario with the colors actually seen in the input.
+pub fn check_scenario(scenario: &Scenario, seen: &HashSet<Color>, colors: &Colors) -> Vec<String> {
+    let mut warnings = Vec::new();
+    for name in scenario.limits.keys() {
+        if !colors.get(name).is_some_and(|it| seen.contains(&it)) {
+            warnings.push(format!("'{name}' not in input"));
+        }
+    }
+    let mut unlimited: Vec<&str> = seen
+        .iter()
+        .map(|it| colors.name(*it))
+        .filter(|it| !scenario.limits.contains_key(*it))
+        .collect();
+    unlimited.sort();
+    for name in unlimited {
+        match scenario.missing {
+            MissingColor::Reject => warnings.push(format!("'{name}' has no limit, rejected")),
+            MissingColor::Unlimited => warnings.push(format!("'{name}' has no limit, unlimited")),
+        }
+    }
+    return warnings;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn limit_spec() {
+        let scenario = parse_limit_spec("red=12, green = 13", MissingColor::Reject).unwrap();
+
+        assert_eq!(
+            BTreeMap::from([("red".to_string(), 12), ("green".to_string(), 13)]),
+            scenario.limits
+        );
+        assert!(parse_limit_spec("red=12,red=3", MissingColor::Reject).is_err());
+        assert!(parse_limit_spec("red:12", MissingColor::Reject).is_err());
+        assert!(parse_limit_spec("red=-1", MissingColor::Reject).is_err());
+    }
+
+    #[test]
+    fn config_formats() {
+        let toml = r#"
+            [[scenarios]]
+            name = "part 1"
+            limits = { red = 12, green = 13, blue = 14 }
+
+            [[scenarios]]
+            name = "only red"
+            limits = { red = 20 }
+            missing = "unlimited"
+        "#;
+        let json = r#"{ "scenarios": [
+            { "name": "part 1", "limits": { "red": 12, "green": 13, "blue": 14 } },
+            { "name": "only red", "limits": { "red": 20 }, "missing": "unlimited" }
+        ] }"#;
+
+        let from_toml: Config = toml::from_str(toml).unwrap();
+        let from_json: Config = serde_json::from_str(json).unwrap();
+
+        assert_eq!(from_toml.scenarios, from_json.scenarios);
+        assert_eq!(MissingColor::Reject, from_toml.scenarios[0].missing);
+        assert_eq!(MissingColor::Unlimited, from_toml.scenarios[1].missing);
+    }
+}
@@ -0,0 +1,198 @@
+use std::collections::HashMap;
+
+use crate::{Color, Match};
+
+/// Bag contents, number of cubes per color.
+pub type Bag = HashMap<Color, u32>;
+
+/// What we know about a bag from the games that were played with it.
+///
+/// Every handful is a lower bound for the colors it contains, so the only constraint a
+/// game puts on the bag is the per-color maximum over its handfuls.
+#[derive(Clone, Default, Debug)]
+pub struct BagConstraints {
+    lower: HashMap<Color, u32>,
+}
+
+impl BagConstraints {
+    pub fn from_game(game: &Match) -> BagConstraints {
+        let mut result = BagConstraints::default();
+        result.add_game(game);
+        return result;
+    }
+
+    /// Constraints for a bag that is known to be shared by all the given games.
+    pub fn from_shared(games: &[Match]) -> BagConstraints {
+        let mut result = BagConstraints::default();
+        for game in games {
+            result.add_game(game);
+        }
+        return result;
+    }
+
+    pub fn add_game(&mut self, game: &Match) {
+        for set in game.sets.iter() {
+            for (color, count) in set {
+                let bound = self.lower.entry(*color).or_insert(0);
+                *bound = (*bound).max(*count);
+            }
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn lower_bound(&self, color: Color) -> u32 {
+        return self.lower.get(&color).copied().unwrap_or(0);
+    }
+
+    /// Lower bounds for all given colors, including the ones never drawn.
+    #[allow(dead_code)]
+    pub fn lower_bounds(&self, colors: &[Color]) -> Vec<(Color, u32)> {
+        return colors
+            .iter()
+            .map(|color| (*color, self.lower_bound(*color)))
+            .collect();
+    }
+
+    /// The smallest bag that could have produced the games.
+    pub fn minimal_bag(&self) -> Bag {
+        return self.lower.clone();
+    }
+
+    #[allow(dead_code)]
+    pub fn is_consistent(&self, bag: &Bag) -> bool {
+        return self
+            .lower
+            .iter()
+            .all(|(color, bound)| bag.get(color).copied().unwrap_or(0) >= *bound);
+    }
+
+    /// All bags with exactly `total` cubes that are consistent with the games.
+    ///
+    /// Cubes may be of any of the given colors, plus the colors that were drawn.
+    pub fn feasible_bags(&self, colors: &[Color], total: u32) -> Vec<Bag> {
+        let mut colors = colors.to_vec();
+        colors.extend(self.lower.keys());
+        colors.sort();
+        colors.dedup();
+
+        let required: u32 = self.lower.values().sum();
+        if required > total {
+            return Vec::new();
+        }
+
+        let mut result = Vec::new();
+        let mut bag = self.minimal_bag();
+        distribute(&colors, total - required, &mut bag, &mut result);
+        return result;
+    }
+}
+
+/// Enumerates every way to add `spare` cubes of the given colors on top of `bag`.
+///
+/// Colors only get an entry once they hold cubes or were already in `bag`, so drawing
+/// "0 green" keeps green in the bag just like `minimal_bag` does.
+fn distribute(colors: &[Color], spare: u32, bag: &mut Bag, result: &mut Vec<Bag>) {
+    let Some((color, rest)) = colors.split_first() else {
+        if spare == 0 {
+            result.push(bag.clone());
+        }
+        return;
+    };
+    let base = bag.get(color).copied();
+    let extras = if rest.is_empty() {
+        spare..=spare
+    } else {
+        0..=spare
+    };
+    for extra in extras {
+        if base.is_none() && extra == 0 {
+            bag.remove(color);
+        } else {
+            bag.insert(*color, base.unwrap_or(0) + extra);
+        }
+        distribute(rest, spare - extra, bag, result);
+    }
+    match base {
+        Some(base) => bag.insert(*color, base),
+        None => bag.remove(color),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::record::Duplicates;
+    use crate::{parse_matches, Colors};
+
+    const INPUT: &str = "Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green
+                         Game 2: 1 blue, 2 green; 3 green, 4 blue, 1 red; 1 green, 1 blue";
+
+    #[test]
+    fn single_game() {
+        let mut colors = Colors::default();
+        let games = parse_matches(INPUT, &mut colors, Duplicates::Last).unwrap();
+        let [red, green, blue] = ["red", "green", "blue"].map(|it| colors.get(it).unwrap());
+        let purple = colors.intern("purple");
+
+        let constraints = BagConstraints::from_game(&games[0]);
+
+        assert_eq!(
+            Bag::from([(red, 4), (green, 2), (blue, 6)]),
+            constraints.minimal_bag()
+        );
+        assert_eq!(
+            vec![(red, 4), (purple, 0)],
+            constraints.lower_bounds(&[red, purple])
+        );
+        assert!(constraints.is_consistent(&Bag::from([(red, 4), (green, 9), (blue, 6)])));
+        assert!(!constraints.is_consistent(&Bag::from([(red, 4), (blue, 6)])));
+    }
+
+    #[test]
+    fn feasible_bags() {
+        let mut colors = Colors::default();
+        let games = parse_matches(INPUT, &mut colors, Duplicates::Last).unwrap();
+        let purple = colors.intern("purple");
+
+        let constraints = BagConstraints::from_game(&games[0]);
+
+        assert!(constraints.feasible_bags(&[], 11).is_empty());
+        assert_eq!(
+            vec![constraints.minimal_bag()],
+            constraints.feasible_bags(&[], 12)
+        );
+        // two spare cubes over four colors
+        let bags = constraints.feasible_bags(&[purple], 14);
+        assert_eq!(10, bags.len());
+        assert!(bags.iter().all(|bag| bag.values().sum::<u32>() == 14));
+        assert!(bags.iter().all(|bag| constraints.is_consistent(bag)));
+
+        let games = parse_matches(
+            "Game 1: 0 green, 3 red; 2 blue",
+            &mut colors,
+            Duplicates::Last,
+        )
+        .unwrap();
+        let constraints = BagConstraints::from_game(&games[0]);
+        assert_eq!(
+            vec![constraints.minimal_bag()],
+            constraints.feasible_bags(&[], 5)
+        );
+    }
+
+    #[test]
+    fn shared_bag() {
+        let mut colors = Colors::default();
+        let games = parse_matches(INPUT, &mut colors, Duplicates::Last).unwrap();
+        let [red, green, blue] = ["red", "green", "blue"].map(|it| colors.get(it).unwrap());
+
+        let constraints = BagConstraints::from_shared(&games);
+
+        assert_eq!(
+            Bag::from([(red, 4), (green, 3), (blue, 6)]),
+            constraints.minimal_bag()
+        );
+        assert!(!BagConstraints::from_game(&games[1]).is_consistent(&Bag::from([(green, 2)])));
+        assert_eq!(3, constraints.feasible_bags(&[], 14).len());
+    }
+}
@@ -1,65 +1,117 @@
-use std::collections::HashMap;
+#![allow(clippy::needless_return)]
 
-#[derive(Clone, Copy, PartialEq, Eq, Hash)]
-enum Color {
-    Red,
-    Green,
-    Blue,
+mod config;
+mod inference;
+mod plausibility;
+mod record;
+
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+
+use config::Scenario;
+use record::Duplicates;
+use serde::Deserialize;
+
+/// Interned color name, see [`Colors`].
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+struct Color(u32);
+
+/// Interner for color names, so games can use any number of colors.
+#[derive(Default)]
+struct Colors {
+    names: Vec<String>,
+    ids: HashMap<String, Color>,
 }
 
-struct Match {
-    id: u32,
-    sets: Vec<HashMap<Color, u32>>,
+impl Colors {
+    fn intern(&mut self, name: &str) -> Color {
+        if let Some(color) = self.ids.get(name) {
+            return *color;
+        }
+        let color = Color(u32::try_from(self.names.len()).unwrap());
+        self.names.push(name.to_string());
+        self.ids.insert(name.to_string(), color);
+        return color;
+    }
+
+    fn get(&self, name: &str) -> Option<Color> {
+        return self.ids.get(name).copied();
+    }
+
+    fn name(&self, color: Color) -> &str {
+        return &self.names[color.0 as usize];
+    }
+}
+
+/// What to do with colors that show up in a game but not in the limit.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum MissingColor {
+    #[default]
+    Reject,
+    Unlimited,
 }
 
-fn parse_count_color(input: &str) -> (Color, u32) {
-    let parts: Vec<&str> = input.trim().split(" ").collect();
-    let count: u32 = parts[0].parse().unwrap();
-    let color = match parts[1] {
-        "red" => Color::Red,
-        "green" => Color::Green,
-        "blue" => Color::Blue,
-        _ => panic!(),
-    };
-    return (color, count);
+/// The bag from the puzzle, used when no `--limit` or `--config` is given.
+const DEFAULT_LIMIT: [(&str, u32); 3] = [("red", 12), ("green", 13), ("blue", 14)];
+
+struct Limit {
+    counts: HashMap<Color, u32>,
+    missing: MissingColor,
 }
 
-fn parse_set(input: &str) -> HashMap<Color, u32> {
-    let mut result: HashMap<Color, u32> = HashMap::new();
-    for (color, count) in input.split(",").map(parse_count_color) {
-        result.insert(color, count);
+impl Limit {
+    fn new<S: AsRef<str>>(
+        colors: &mut Colors,
+        counts: &[(S, u32)],
+        missing: MissingColor,
+    ) -> Limit {
+        let counts = counts
+            .iter()
+            .map(|(name, count)| (colors.intern(name.as_ref()), *count))
+            .collect();
+        return Limit { counts, missing };
     }
-    return result;
 }
 
-fn parse_match(line: &str) -> Match {
-    let game_sets: Vec<&str> = line.split(":").collect();
-    let game = game_sets[0];
-    let sets = game_sets[1];
-    let id: u32 = game.trim()[5..].parse().unwrap();
-    let sets: Vec<HashMap<Color, u32>> = sets.split(";").map(parse_set).collect();
-    return Match { id, sets };
+struct Match {
+    id: u32,
+    sets: Vec<HashMap<Color, u32>>,
 }
 
-fn parse_matches(input: &str) -> Vec<Match> {
-    return input.lines().map(parse_match).collect();
+fn parse_match(line: &str, colors: &mut Colors, duplicates: Duplicates) -> Result<Match, String> {
+    let record = record::GameRecord::parse(line, colors)?;
+    return record.normalized(duplicates)?.to_match();
 }
 
-fn are_sets_valid(sets: &Vec<HashMap<Color, u32>>, limit: &HashMap<Color, u32>) -> bool {
+fn parse_matches(
+    input: &str,
+    colors: &mut Colors,
+    duplicates: Duplicates,
+) -> Result<Vec<Match>, String> {
+    return input
+        .lines()
+        .map(|it| parse_match(it, colors, duplicates))
+        .collect();
+}
+
+fn are_sets_valid(sets: &Vec<HashMap<Color, u32>>, limit: &Limit) -> bool {
     for set in sets {
         for (color, count) in set {
-            if !limit.contains_key(&color) || limit[color] < *count {
-                return false;
+            match limit.counts.get(color) {
+                Some(max) if max < count => return false,
+                None if limit.missing == MissingColor::Reject => return false,
+                _ => {}
             }
         }
     }
     return true;
 }
 
-fn sum_valid_match_ids(input: Vec<Match>, limit: HashMap<Color, u32>) -> u32 {
+fn sum_valid_match_ids(input: &[Match], limit: &Limit) -> u32 {
     let mut sum = 0;
     for game in input.iter() {
-        if are_sets_valid(&game.sets, &limit) {
+        if are_sets_valid(&game.sets, limit) {
             sum += game.id;
         }
     }
@@ -67,40 +119,253 @@ fn sum_valid_match_ids(input: Vec<Match>, limit: HashMap<Color, u32>) -> u32 {
 }
 
 fn game_power(input: &Match) -> u32 {
-    let mut maxima: HashMap<Color, u32> = HashMap::new();
-    for set in input.sets.iter() {
-        for (color, count) in set {
-            let max = match maxima.get(color) {
-                Some(value) => *count.max(value),
-                _ => *count,
-            };
-            maxima.insert(*color, max);
-        }
-    }
-    return maxima.into_values().reduce(|a, b| a * b).unwrap();
+    let bag = inference::BagConstraints::from_game(input).minimal_bag();
+    return bag.into_values().reduce(|a, b| a * b).unwrap();
 }
 
 fn sum_powers(input: Vec<Match>) -> u32 {
     return input.iter().map(game_power).sum();
 }
 
-fn solve(input: &str, limit: HashMap<Color, u32>) -> u32 {
-    let matches = parse_matches(input);
-    return sum_valid_match_ids(matches, limit);
+fn seen_colors(input: &[Match]) -> HashSet<Color> {
+    return input
+        .iter()
+        .flat_map(|game| game.sets.iter().flat_map(|set| set.keys().copied()))
+        .collect();
+}
+
+struct ScenarioResult {
+    valid_games: usize,
+    id_sum: u32,
+    warnings: Vec<String>,
+}
+
+fn evaluate_scenarios(
+    input: &[Match],
+    colors: &mut Colors,
+    scenarios: &[Scenario],
+) -> Vec<ScenarioResult> {
+    let seen = seen_colors(input);
+    let mut results = Vec::new();
+    for scenario in scenarios {
+        let warnings = config::check_scenario(scenario, &seen, colors);
+        let limit = scenario.limit(colors);
+        let valid_games = input
+            .iter()
+            .filter(|game| are_sets_valid(&game.sets, &limit))
+            .count();
+        let id_sum = sum_valid_match_ids(input, &limit);
+        results.push(ScenarioResult {
+            valid_games,
+            id_sum,
+            warnings,
+        });
+    }
+    return results;
+}
+
+fn print_scenario_table(scenarios: &[Scenario], results: &[ScenarioResult]) {
+    let width = scenarios
+        .iter()
+        .map(|it| it.name.len())
+        .chain(["scenario".len()])
+        .max()
+        .unwrap();
+    println!(
+        "{:width$} | missing   | valid | id sum | warnings",
+        "scenario"
+    );
+    println!("{:-<width$}-+-----------+-------+--------+---------", "");
+    for (scenario, result) in scenarios.iter().zip(results) {
+        let missing = match scenario.missing {
+            MissingColor::Reject => "reject",
+            MissingColor::Unlimited => "unlimited",
+        };
+        println!(
+            "{:width$} | {:9} | {:5} | {:6} | {}",
+            scenario.name,
+            missing,
+            result.valid_games,
+            result.id_sum,
+            result.warnings.join("; ")
+        );
+    }
+}
+
+/// Prints games from most to least surprising under the bag, surprisal in nats.
+fn print_surprise(matches: &[Match], bag: &inference::Bag) {
+    for (id, surprisal) in plausibility::rank_by_surprise(matches, bag) {
+        println!("game {id}: {surprisal:.3}");
+    }
+}
+
+fn solve(
+    input: &str,
+    limit: &[(&str, u32)],
+    missing: MissingColor,
+    duplicates: Duplicates,
+) -> Result<u32, String> {
+    let mut colors = Colors::default();
+    let matches = parse_matches(input, &mut colors, duplicates)?;
+    let limit = Limit::new(&mut colors, limit, missing);
+    return Ok(sum_valid_match_ids(&matches, &limit));
+}
+
+fn solve2(input: &str, duplicates: Duplicates) -> Result<u32, String> {
+    let matches = parse_matches(input, &mut Colors::default(), duplicates)?;
+    return Ok(sum_powers(matches));
 }
 
-fn solve2(input: &str) -> u32 {
-    let matches = parse_matches(input);
-    return sum_powers(matches);
+struct Args {
+    input: PathBuf,
+    scenarios: Vec<Scenario>,
+    missing: MissingColor,
+    duplicates: Duplicates,
+    json: bool,
+    rank_surprise: bool,
+    estimate_bag: Option<u32>,
+}
+
+const USAGE: &str =
+    "usage: day02 [INPUT] [--limit red=12,green=13,...]... [--config FILE]... [--unlimited]
+             [--duplicates last|merge|error] [--json] [--rank-surprise] [--estimate-bag MAX_CUBES]";
+
+fn parse_args(args: impl Iterator<Item = String>) -> Result<Args, String> {
+    let mut input = None;
+    let mut specs = Vec::new();
+    let mut scenarios = Vec::new();
+    let mut missing = MissingColor::Reject;
+    let mut duplicates = Duplicates::Last;
+    let mut json = false;
+    let mut rank_surprise = false;
+    let mut estimate_bag = None;
+    let mut args = args;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--limit" => specs.push(args.next().ok_or("--limit needs a value")?),
+            "--config" => {
+                let path = args.next().ok_or("--config needs a value")?;
+                scenarios.append(&mut config::load_scenarios(path.as_ref())?);
+            }
+            "--unlimited" => missing = MissingColor::Unlimited,
+            "--duplicates" => {
+                duplicates = match args.next().as_deref() {
+                    Some("last") => Duplicates::Last,
+                    Some("merge") => Duplicates::Merge,
+                    Some("error") => Duplicates::Error,
+                    _ => return Err("--duplicates needs last, merge or error".to_string()),
+                }
+            }
+            "--json" => json = true,
+            "--rank-surprise" => rank_surprise = true,
+            "--estimate-bag" => {
+                let max = args.next().ok_or("--estimate-bag needs a value")?;
+                let max = max
+                    .parse()
+                    .map_err(|err| format!("invalid cube count '{max}': {err}"))?;
+                estimate_bag = Some(max);
+            }
+            _ if arg.starts_with("--") => return Err(format!("unknown option '{arg}'")),
+            _ if input.is_none() => input = Some(PathBuf::from(arg)),
+            _ => return Err(format!("unexpected argument '{arg}'")),
+        }
+    }
+    for spec in specs {
+        scenarios.push(config::parse_limit_spec(&spec, missing)?);
+    }
+    return Ok(Args {
+        input: input.unwrap_or_else(|| PathBuf::from("input.txt")),
+        scenarios,
+        missing,
+        duplicates,
+        json,
+        rank_surprise,
+        estimate_bag,
+    });
+}
+
+fn fail<T>(err: String) -> T {
+    eprintln!("{err}");
+    std::process::exit(1);
 }
 
 fn main() {
-    let input = std::fs::read_to_string("input.txt").unwrap();
-    let limit = HashMap::from([(Color::Red, 12), (Color::Green, 13), (Color::Blue, 14)]);
-    let output = solve(input.as_str(), limit);
-    println!("{output}");
+    let args = parse_args(std::env::args().skip(1)).unwrap_or_else(|err| {
+        eprintln!("{err}\n{USAGE}");
+        std::process::exit(2);
+    });
+    let input = std::fs::read_to_string(&args.input).unwrap();
+
+    if args.json {
+        let mut colors = Colors::default();
+        for line in input.lines() {
+            let record = record::GameRecord::parse(line, &mut colors)
+                .and_then(|it| it.normalized(args.duplicates))
+                .unwrap_or_else(fail);
+            println!("{}", record.to_json(&colors));
+        }
+        return;
+    }
+
+    let mut colors = Colors::default();
+    let matches = parse_matches(input.as_str(), &mut colors, args.duplicates).unwrap_or_else(fail);
 
-    let output2 = solve2(input.as_str());
+    if args.rank_surprise {
+        if args.scenarios.is_empty() {
+            let bag = Limit::new(&mut colors, &DEFAULT_LIMIT, args.missing).counts;
+            print_surprise(&matches, &bag);
+        }
+        for scenario in args.scenarios.iter() {
+            println!("{}:", scenario.name);
+            let bag = scenario.limit(&mut colors).counts;
+            print_surprise(&matches, &bag);
+        }
+        return;
+    }
+
+    if let Some(max) = args.estimate_bag {
+        match plausibility::max_likelihood_bag(&matches, &[], max) {
+            Some(estimate) => {
+                let mut cubes: Vec<(&str, u32)> = estimate
+                    .bag
+                    .iter()
+                    .map(|(color, count)| (colors.name(*color), *count))
+                    .collect();
+                cubes.sort();
+                let cubes: Vec<String> = cubes
+                    .iter()
+                    .map(|(name, count)| format!("{count} {name}"))
+                    .collect();
+                println!(
+                    "{} ({} cubes, log-likelihood {:.3})",
+                    cubes.join(", "),
+                    estimate.size,
+                    estimate.log_likelihood
+                );
+            }
+            None => {
+                eprintln!("no bag with at most {max} cubes could have produced the games");
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    if args.scenarios.is_empty() {
+        let output = solve(
+            input.as_str(),
+            &DEFAULT_LIMIT,
+            args.missing,
+            args.duplicates,
+        )
+        .unwrap_or_else(fail);
+        println!("{output}");
+    } else {
+        let results = evaluate_scenarios(&matches, &mut colors, &args.scenarios);
+        print_scenario_table(&args.scenarios, &results);
+    }
+
+    let output2 = solve2(input.as_str(), args.duplicates).unwrap_or_else(fail);
     println!("{output2}");
 }
 
@@ -115,10 +380,15 @@ mod tests {
                            Game 3: 8 green, 6 blue, 20 red; 5 blue, 4 red, 13 green; 5 green, 1 red
                            Game 4: 1 green, 3 red, 6 blue; 3 green, 6 red; 3 green, 15 blue, 14 red
                            Game 5: 6 red, 1 blue, 3 green; 2 blue, 1 red, 2 green";
-        let limit = HashMap::from([(Color::Red, 12), (Color::Green, 13), (Color::Blue, 14)]);
         let expected_output = 8;
 
-        let output = solve(input, limit);
+        let output = solve(
+            input,
+            &DEFAULT_LIMIT,
+            MissingColor::Reject,
+            Duplicates::Last,
+        )
+        .unwrap();
 
         assert_eq!(expected_output, output);
     }
@@ -132,8 +402,69 @@ mod tests {
                            Game 5: 6 red, 1 blue, 3 green; 2 blue, 1 red, 2 green";
         let expected_output = 2286;
 
-        let output = solve2(input);
+        let output = solve2(input, Duplicates::Last).unwrap();
 
         assert_eq!(expected_output, output);
     }
+
+    #[test]
+    fn custom_colors() {
+        let input = "Game 1: 7 purple, 2 red; 1 orange
+                     Game 2: 3 red
+                     Game 3: 9 purple";
+        let limit = [("red", 3), ("purple", 8)];
+
+        let duplicates = Duplicates::Last;
+        assert_eq!(
+            Ok(2),
+            solve(input, &limit, MissingColor::Reject, duplicates)
+        );
+        assert_eq!(
+            Ok(3),
+            solve(input, &limit, MissingColor::Unlimited, duplicates)
+        );
+        assert_eq!(Ok(7 * 2 + 3 + 9), solve2(input, duplicates));
+    }
+
+    #[test]
+    fn duplicate_colors() {
+        let input = "Game 1: 13 red, 12 red; 1 blue";
+        let limit = [("red", 12), ("blue", 14)];
+        let reject = MissingColor::Reject;
+
+        assert_eq!(Ok(1), solve(input, &limit, reject, Duplicates::Last));
+        assert_eq!(Ok(0), solve(input, &limit, reject, Duplicates::Merge));
+        assert_eq!(Ok(25), solve2(input, Duplicates::Merge));
+        assert!(solve(input, &limit, reject, Duplicates::Error).is_err());
+        assert!(solve2(input, Duplicates::Error).is_err());
+    }
+
+    #[test]
+    fn scenarios() {
+        let input = "Game 1: 7 purple, 2 red; 1 orange
+                     Game 2: 3 red
+                     Game 3: 9 purple";
+        let scenarios = [
+            config::parse_limit_spec("red=3,purple=8", MissingColor::Reject).unwrap(),
+            config::parse_limit_spec("red=3,blue=1", MissingColor::Unlimited).unwrap(),
+        ];
+        let mut colors = Colors::default();
+        let matches = parse_matches(input, &mut colors, Duplicates::Last).unwrap();
+
+        let results = evaluate_scenarios(&matches, &mut colors, &scenarios);
+
+        assert_eq!(1, results[0].valid_games);
+        assert_eq!(2, results[0].id_sum);
+        assert_eq!(vec!["'orange' has no limit, rejected"], results[0].warnings);
+        assert_eq!(3, results[1].valid_games);
+        assert_eq!(6, results[1].id_sum);
+        assert_eq!(
+            vec![
+                "'blue' not in input",
+                "'orange' has no limit, unlimited",
+                "'purple' has no limit, unlimited"
+            ],
+            results[1].warnings
+        );
+    }
 }
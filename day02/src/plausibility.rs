@@ -0,0 +1,169 @@
+use std::collections::HashMap;
+use std::f64::consts::PI;
+
+use crate::inference::{Bag, BagConstraints};
+use crate::{Color, Match};
+
+/// ln(n!), exact summation for small n, Stirling series otherwise.
+fn ln_factorial(n: u64) -> f64 {
+    if n < 16 {
+        return (2..=n).map(|it| (it as f64).ln()).sum();
+    }
+    let n = n as f64;
+    return n * n.ln() - n + 0.5 * (2.0 * PI * n).ln() + 1.0 / (12.0 * n)
+        - 1.0 / (360.0 * n.powi(3))
+        + 1.0 / (1260.0 * n.powi(5));
+}
+
+fn ln_choose(n: u64, k: u64) -> f64 {
+    if k > n {
+        return f64::NEG_INFINITY;
+    }
+    return ln_factorial(n) - ln_factorial(k) - ln_factorial(n - k);
+}
+
+/// Log-probability of drawing exactly this handful from the bag, without replacement.
+///
+/// This is the multivariate hypergeometric distribution. Impossible handfuls yield
+/// negative infinity.
+pub fn handful_log_probability(set: &HashMap<Color, u32>, bag: &Bag) -> f64 {
+    let bag_size: u64 = bag.values().map(|it| *it as u64).sum();
+    let handful_size: u64 = set.values().map(|it| *it as u64).sum();
+    let mut result = -ln_choose(bag_size, handful_size);
+    for (color, count) in set {
+        let available = bag.get(color).copied().unwrap_or(0);
+        result += ln_choose(available as u64, *count as u64);
+    }
+    return result;
+}
+
+/// Log-likelihood of a whole game. Cubes go back into the bag between handfuls, so these
+/// are independent.
+pub fn game_log_likelihood(game: &Match, bag: &Bag) -> f64 {
+    return game
+        .sets
+        .iter()
+        .map(|set| handful_log_probability(set, bag))
+        .sum();
+}
+
+/// Games ordered from most to least surprising under the bag, with their surprisal
+/// (negative log-likelihood, in nats). Impossible games come first with infinite surprisal.
+pub fn rank_by_surprise(games: &[Match], bag: &Bag) -> Vec<(u32, f64)> {
+    let mut result: Vec<(u32, f64)> = games
+        .iter()
+        .map(|game| (game.id, -game_log_likelihood(game, bag)))
+        .collect();
+    result.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+    return result;
+}
+
+pub struct Estimate {
+    pub bag: Bag,
+    pub size: u32,
+    pub log_likelihood: f64,
+}
+
+/// The most likely bag with at most `max_size` cubes of the given colors (plus the ones
+/// that were drawn) to have produced the games, which are assumed to share the bag.
+///
+/// On ties, the smaller bag wins.
+pub fn max_likelihood_bag(games: &[Match], colors: &[Color], max_size: u32) -> Option<Estimate> {
+    let constraints = BagConstraints::from_shared(games);
+    let min_size: u32 = constraints.minimal_bag().values().sum();
+    let mut best: Option<Estimate> = None;
+    for size in min_size..=max_size {
+        for bag in constraints.feasible_bags(colors, size) {
+            let log_likelihood: f64 = games.iter().map(|it| game_log_likelihood(it, &bag)).sum();
+            if best
+                .as_ref()
+                .is_none_or(|it| log_likelihood > it.log_likelihood)
+            {
+                best = Some(Estimate {
+                    bag,
+                    size,
+                    log_likelihood,
+                });
+            }
+        }
+    }
+    return best;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::record::Duplicates;
+    use crate::{parse_matches, Colors};
+
+    #[test]
+    fn hypergeometric() {
+        let mut colors = Colors::default();
+        let [red, blue] = ["red", "blue"].map(|it| colors.intern(it));
+        let bag = Bag::from([(red, 2), (blue, 1)]);
+
+        let p = handful_log_probability(&HashMap::from([(red, 1), (blue, 1)]), &bag).exp();
+        assert!((p - 2.0 / 3.0).abs() < 1e-12);
+        let p = handful_log_probability(&HashMap::from([(red, 2)]), &bag).exp();
+        assert!((p - 1.0 / 3.0).abs() < 1e-12);
+        let p = handful_log_probability(&HashMap::from([(blue, 2)]), &bag);
+        assert_eq!(f64::NEG_INFINITY, p);
+
+        // C(40, 20) * C(30, 10) / C(70, 30)
+        let bag = Bag::from([(red, 40), (blue, 30)]);
+        let p = handful_log_probability(&HashMap::from([(red, 20), (blue, 10)]), &bag).exp();
+        assert!((p - 0.074_828_728_72).abs() < 1e-10);
+    }
+
+    #[test]
+    fn large_counts() {
+        let mut colors = Colors::default();
+        let [red, blue] = ["red", "blue"].map(|it| colors.intern(it));
+        let bag = Bag::from([(red, 1_000_000), (blue, 1_000_000)]);
+
+        let p = handful_log_probability(&HashMap::from([(red, 5000), (blue, 5000)]), &bag);
+
+        assert!(p.is_finite());
+        assert!(p < 0.0);
+    }
+
+    #[test]
+    fn surprise() {
+        let input = "Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green
+                     Game 2: 1 blue, 2 green; 3 green, 4 blue, 1 red; 1 green, 1 blue
+                     Game 3: 8 green, 6 blue, 20 red; 5 blue, 4 red, 13 green; 5 green, 1 red";
+        let mut colors = Colors::default();
+        let games = parse_matches(input, &mut colors, Duplicates::Last).unwrap();
+        let [red, green, blue] = ["red", "green", "blue"].map(|it| colors.get(it).unwrap());
+        let bag = Bag::from([(red, 12), (green, 13), (blue, 14)]);
+
+        let ranking = rank_by_surprise(&games, &bag);
+
+        assert_eq!(
+            vec![3, 1, 2],
+            ranking.iter().map(|it| it.0).collect::<Vec<u32>>()
+        );
+        assert_eq!(f64::INFINITY, ranking[0].1);
+        assert!(ranking[1].1 > ranking[2].1);
+    }
+
+    #[test]
+    fn max_likelihood() {
+        let mut colors = Colors::default();
+        let games = parse_matches(
+            "Game 1: 2 red; 2 red; 2 red; 1 blue",
+            &mut colors,
+            Duplicates::Last,
+        )
+        .unwrap();
+        let [red, blue] = ["red", "blue"].map(|it| colors.get(it).unwrap());
+
+        let estimate = max_likelihood_bag(&games, &[], 10).unwrap();
+
+        // Red pairs every time favor a red-heavy bag, but a single blue cube is
+        // unlikely to be drawn from a very large one.
+        assert_eq!(Bag::from([(red, 7), (blue, 1)]), estimate.bag);
+        assert_eq!(8, estimate.size);
+        assert!(max_likelihood_bag(&games, &[], 2).is_none());
+    }
+}
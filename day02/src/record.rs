@@ -0,0 +1,362 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Color, Colors, Match};
+
+/// A single "<count> <color>" entry of a handful.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Draw {
+    pub count: u32,
+    pub color: Color,
+}
+
+/// The draws of one handful, in the order they were recorded.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct Handful {
+    pub draws: Vec<Draw>,
+}
+
+/// A game exactly as it was recorded, including draw order and repeated colors.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct GameRecord {
+    pub id: u32,
+    pub handfuls: Vec<Handful>,
+}
+
+/// How to treat a color that appears more than once in the same handful.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Duplicates {
+    /// The count recorded last wins, like the original solution did.
+    Last,
+    Merge,
+    Error,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[allow(dead_code)]
+pub enum Change {
+    Id {
+        old: u32,
+        new: u32,
+    },
+    HandfulAdded {
+        handful: usize,
+    },
+    HandfulRemoved {
+        handful: usize,
+    },
+    Count {
+        handful: usize,
+        color: Color,
+        old: u32,
+        new: u32,
+    },
+}
+
+fn parse_draw(input: &str, colors: &mut Colors) -> Result<Draw, String> {
+    let mut parts = input.split_whitespace();
+    let (Some(count), Some(color), None) = (parts.next(), parts.next(), parts.next()) else {
+        return Err(format!(
+            "expected '<count> <color>', got '{}'",
+            input.trim()
+        ));
+    };
+    let count: u32 = count
+        .parse()
+        .map_err(|err| format!("invalid count in '{}': {err}", input.trim()))?;
+    return Ok(Draw {
+        count,
+        color: colors.intern(color),
+    });
+}
+
+impl Handful {
+    /// Total count per color, duplicates summed up.
+    pub fn counts(&self) -> Result<HashMap<Color, u32>, String> {
+        let mut result = HashMap::new();
+        for (i, draw) in self.draws.iter().enumerate() {
+            let count = result.entry(draw.color).or_insert(0u32);
+            *count = count
+                .checked_add(draw.count)
+                .ok_or_else(|| format!("draw {} overflows the count of its color", i + 1))?;
+        }
+        return Ok(result);
+    }
+
+    /// Collapses repeated colors into the position of their first occurrence.
+    pub fn normalized(&self, duplicates: Duplicates) -> Result<Handful, String> {
+        let mut draws: Vec<Draw> = Vec::new();
+        for (i, draw) in self.draws.iter().enumerate() {
+            match draws.iter_mut().find(|it| it.color == draw.color) {
+                Some(_) if duplicates == Duplicates::Error => {
+                    return Err(format!("draw {} repeats an earlier color", i + 1));
+                }
+                Some(existing) if duplicates == Duplicates::Last => existing.count = draw.count,
+                Some(existing) => {
+                    existing.count = existing.count.checked_add(draw.count).ok_or_else(|| {
+                        format!("draw {} overflows the count of its color", i + 1)
+                    })?;
+                }
+                None => draws.push(*draw),
+            }
+        }
+        return Ok(Handful { draws });
+    }
+}
+
+impl GameRecord {
+    pub fn parse(line: &str, colors: &mut Colors) -> Result<GameRecord, String> {
+        let line = line.trim();
+        let (game, handfuls) = line
+            .split_once(":")
+            .ok_or_else(|| format!("missing ':' in '{line}'"))?;
+        let id = game
+            .trim()
+            .strip_prefix("Game ")
+            .and_then(|it| it.trim().parse().ok())
+            .ok_or_else(|| format!("expected 'Game <id>', got '{game}'"))?;
+        let mut result = GameRecord {
+            id,
+            handfuls: Vec::new(),
+        };
+        for handful in handfuls.split(";") {
+            let draws = handful
+                .split(",")
+                .map(|it| parse_draw(it, colors))
+                .collect::<Result<Vec<Draw>, String>>()
+                .map_err(|err| format!("game {id}: {err}"))?;
+            result.handfuls.push(Handful { draws });
+        }
+        return Ok(result);
+    }
+
+    pub fn normalized(&self, duplicates: Duplicates) -> Result<GameRecord, String> {
+        let handfuls = self
+            .handfuls
+            .iter()
+            .map(|it| it.normalized(duplicates))
+            .collect::<Result<Vec<Handful>, String>>()
+            .map_err(|err| format!("game {}: {err}", self.id))?;
+        return Ok(GameRecord {
+            id: self.id,
+            handfuls,
+        });
+    }
+
+    pub fn to_match(&self) -> Result<Match, String> {
+        let sets = self
+            .handfuls
+            .iter()
+            .map(Handful::counts)
+            .collect::<Result<Vec<HashMap<Color, u32>>, String>>()
+            .map_err(|err| format!("game {}: {err}", self.id))?;
+        return Ok(Match { id: self.id, sets });
+    }
+
+    /// Formats the record in the puzzle's "Game N: ..." syntax.
+    #[allow(dead_code)]
+    pub fn display<'a>(&'a self, colors: &'a Colors) -> RecordDisplay<'a> {
+        return RecordDisplay {
+            record: self,
+            colors,
+        };
+    }
+
+    pub fn to_json(&self, colors: &Colors) -> String {
+        let record = JsonRecord {
+            id: self.id,
+            handfuls: self
+                .handfuls
+                .iter()
+                .map(|handful| {
+                    let draws = handful.draws.iter().map(|draw| JsonDraw {
+                        count: draw.count,
+                        color: colors.name(draw.color).to_string(),
+                    });
+                    draws.collect()
+                })
+                .collect(),
+        };
+        return serde_json::to_string(&record).unwrap();
+    }
+
+    #[allow(dead_code)]
+    pub fn from_json(text: &str, colors: &mut Colors) -> Result<GameRecord, String> {
+        let record: JsonRecord = serde_json::from_str(text).map_err(|err| err.to_string())?;
+        let handfuls = record
+            .handfuls
+            .iter()
+            .map(|draws| Handful {
+                draws: draws
+                    .iter()
+                    .map(|draw| Draw {
+                        count: draw.count,
+                        color: colors.intern(&draw.color),
+                    })
+                    .collect(),
+            })
+            .collect();
+        return Ok(GameRecord {
+            id: record.id,
+            handfuls,
+        });
+    }
+}
+
+#[allow(dead_code)]
+pub struct RecordDisplay<'a> {
+    record: &'a GameRecord,
+    colors: &'a Colors,
+}
+
+impl fmt::Display for RecordDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Game {}:", self.record.id)?;
+        for (i, handful) in self.record.handfuls.iter().enumerate() {
+            if i > 0 {
+                write!(f, ";")?;
+            }
+            for (j, draw) in handful.draws.iter().enumerate() {
+                if j > 0 {
+                    write!(f, ",")?;
+                }
+                write!(f, " {} {}", draw.count, self.colors.name(draw.color))?;
+            }
+        }
+        return Ok(());
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct JsonDraw {
+    count: u32,
+    color: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct JsonRecord {
+    id: u32,
+    handfuls: Vec<Vec<JsonDraw>>,
+}
+
+/// Differences between two records, comparing handfuls by position and ignoring draw order.
+#[allow(dead_code)]
+pub fn diff(old: &GameRecord, new: &GameRecord) -> Result<Vec<Change>, String> {
+    let mut result = Vec::new();
+    if old.id != new.id {
+        result.push(Change::Id {
+            old: old.id,
+            new: new.id,
+        });
+    }
+    for (handful, (a, b)) in old.handfuls.iter().zip(new.handfuls.iter()).enumerate() {
+        let a = a.counts()?;
+        let b = b.counts()?;
+        let mut colors: Vec<Color> = a.keys().chain(b.keys()).copied().collect();
+        colors.sort();
+        colors.dedup();
+        for color in colors {
+            let old = a.get(&color).copied().unwrap_or(0);
+            let new = b.get(&color).copied().unwrap_or(0);
+            if old != new {
+                result.push(Change::Count {
+                    handful,
+                    color,
+                    old,
+                    new,
+                });
+            }
+        }
+    }
+    for handful in new.handfuls.len()..old.handfuls.len() {
+        result.push(Change::HandfulRemoved { handful });
+    }
+    for handful in old.handfuls.len()..new.handfuls.len() {
+        result.push(Change::HandfulAdded { handful });
+    }
+    return Ok(result);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let line = "Game 12: 3 blue, 4 red, 2 blue; 1 red; 7 purple";
+        let mut colors = Colors::default();
+
+        let record = GameRecord::parse(line, &mut colors).unwrap();
+
+        assert_eq!(line, record.display(&colors).to_string());
+        let json = record.to_json(&colors);
+        assert_eq!(record, GameRecord::from_json(&json, &mut colors).unwrap());
+    }
+
+    #[test]
+    fn duplicates() {
+        let mut colors = Colors::default();
+        let record = GameRecord::parse("Game 1: 3 red, 1 blue, 2 red", &mut colors).unwrap();
+
+        let merged = record.normalized(Duplicates::Merge).unwrap();
+
+        assert_eq!("Game 1: 5 red, 1 blue", merged.display(&colors).to_string());
+        let last = record.normalized(Duplicates::Last).unwrap();
+        assert_eq!("Game 1: 2 red, 1 blue", last.display(&colors).to_string());
+        assert!(record.normalized(Duplicates::Error).is_err());
+        assert_eq!(
+            Some(&5),
+            record.to_match().unwrap().sets[0].get(&colors.get("red").unwrap())
+        );
+
+        let record = GameRecord::parse("Game 7: 4294967295 red, 1 red", &mut colors).unwrap();
+        assert_eq!(
+            Err("game 7: draw 2 overflows the count of its color".to_string()),
+            record.normalized(Duplicates::Merge)
+        );
+        assert!(record.to_match().is_err());
+        assert!(record.normalized(Duplicates::Last).is_ok());
+    }
+
+    #[test]
+    fn parse_errors() {
+        let mut colors = Colors::default();
+
+        assert!(GameRecord::parse("Game 1 3 red", &mut colors).is_err());
+        assert!(GameRecord::parse("Gam 1: 3 red", &mut colors).is_err());
+        assert!(GameRecord::parse("Game 1: red", &mut colors).is_err());
+        assert!(GameRecord::parse("Game 1: 3 red blue", &mut colors).is_err());
+        assert!(GameRecord::parse("Game 1: x red", &mut colors).is_err());
+    }
+
+    #[test]
+    fn differences() {
+        let mut colors = Colors::default();
+        let old = GameRecord::parse("Game 1: 3 red, 1 blue; 2 green", &mut colors).unwrap();
+        let new = GameRecord::parse("Game 2: 1 blue, 2 red, 1 red", &mut colors).unwrap();
+
+        assert!(diff(&old, &old).unwrap().is_empty());
+        assert_eq!(
+            vec![
+                Change::Id { old: 1, new: 2 },
+                Change::HandfulRemoved { handful: 1 },
+            ],
+            diff(&old, &new).unwrap()
+        );
+        let new = GameRecord::parse("Game 1: 3 red; 2 green; 1 red", &mut colors).unwrap();
+        assert_eq!(
+            vec![
+                Change::Count {
+                    handful: 0,
+                    color: colors.get("blue").unwrap(),
+                    old: 1,
+                    new: 0
+                },
+                Change::HandfulAdded { handful: 2 },
+            ],
+            diff(&old, &new).unwrap()
+        );
+    }
+}
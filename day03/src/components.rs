@@ -0,0 +1,170 @@
+use crate::grid::Point;
+use crate::{Occupant, Schematic};
+
+/// A cluster of symbols and the numbers attached to them.
+///
+/// Symbols link to the numbers and symbols touching them. Numbers touching each other
+/// are not linked, since only symbols turn numbers into parts.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Component {
+    pub numbers: Vec<usize>,
+    pub symbols: Vec<usize>,
+    /// Top-left corner of the bounding box.
+    pub min: Point,
+    /// Bottom-right corner of the bounding box, inclusive.
+    pub max: Point,
+    pub total: i64,
+}
+
+struct DisjointSet {
+    parents: Vec<usize>,
+}
+
+impl DisjointSet {
+    fn find(&mut self, item: usize) -> usize {
+        let mut root = item;
+        while self.parents[root] != root {
+            root = self.parents[root];
+        }
+        let mut item = item;
+        while self.parents[item] != root {
+            item = std::mem::replace(&mut self.parents[item], root);
+        }
+        return root;
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let a = self.find(a);
+        let b = self.find(b);
+        self.parents[a.max(b)] = a.min(b);
+    }
+}
+
+/// All components, ordered by their first symbol.
+pub fn components(schematic: &Schematic) -> Result<Vec<Component>, String> {
+    // Numbers come first in the set, symbols after them.
+    let offset = schematic.numbers.len();
+    let mut set = DisjointSet {
+        parents: (0..offset + schematic.symbols.len()).collect(),
+    };
+    for (index, symbol) in schematic.symbols.iter().enumerate() {
+        for neighbor in schematic.neighbors(symbol.position) {
+            match schematic.cells[neighbor] {
+                Occupant::Number(other) => set.union(offset + index, other as usize),
+                Occupant::Symbol(other) => set.union(offset + index, offset + other as usize),
+                Occupant::Empty => {}
+            }
+        }
+    }
+
+    let mut result: Vec<Component> = Vec::new();
+    let mut component_of_root = vec![usize::MAX; set.parents.len()];
+    for (index, symbol) in schematic.symbols.iter().enumerate() {
+        let root = set.find(offset + index);
+        if component_of_root[root] == usize::MAX {
+            component_of_root[root] = result.len();
+            result.push(Component {
+                numbers: Vec::new(),
+                symbols: Vec::new(),
+                min: symbol.position,
+                max: symbol.position,
+                total: 0,
+            });
+        }
+        let component = &mut result[component_of_root[root]];
+        component.symbols.push(index);
+        extend(component, symbol.position);
+    }
+    for (index, number) in schematic.numbers.iter().enumerate() {
+        let root = set.find(index);
+        if component_of_root[root] == usize::MAX {
+            continue;
+        }
+        let component = &mut result[component_of_root[root]];
+        component.numbers.push(index);
+        component.total = component
+            .total
+            .checked_add(number.value)
+            .ok_or_else(|| format!("total of component {} overflows", component_of_root[root]))?;
+        for position in number.positions.iter() {
+            extend(component, *position);
+        }
+    }
+    return Ok(result);
+}
+
+fn extend(component: &mut Component, position: Point) {
+    component.min.x = component.min.x.min(position.x);
+    component.min.y = component.min.y.min(position.y);
+    component.max.x = component.max.x.max(position.x);
+    component.max.y = component.max.y.max(position.y);
+}
+
+/// Numbers that touch no symbol.
+pub fn orphans(schematic: &Schematic) -> Vec<usize> {
+    return schematic
+        .part_numbers()
+        .iter()
+        .enumerate()
+        .filter(|(_, is_part)| !**is_part)
+        .map(|(index, _)| index)
+        .collect();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+    use crate::rules::Rules;
+
+    #[test]
+    fn sample() {
+        let input = "467..114..
+                     ...*......
+                     ..35..633.
+                     ......#...
+                     617*......
+                     .....+.58.
+                     ..592.....
+                     ......755.
+                     ...$.*....
+                     .664.598..";
+        let schematic = parse(input, &Rules::default()).unwrap();
+        let values = |numbers: &[usize]| {
+            numbers
+                .iter()
+                .map(|it| schematic.numbers[*it].value)
+                .collect::<Vec<i64>>()
+        };
+
+        let components = components(&schematic).unwrap();
+
+        assert_eq!(6, components.len());
+        assert_eq!(vec![467, 35], values(&components[0].numbers));
+        assert_eq!(467 + 35, components[0].total);
+        assert_eq!(Point { x: 0, y: 0 }, components[0].min);
+        assert_eq!(Point { x: 3, y: 2 }, components[0].max);
+        assert_eq!(vec![633], values(&components[1].numbers));
+        assert_eq!(vec![617], values(&components[2].numbers));
+        assert_eq!(vec![592], values(&components[3].numbers));
+        assert_eq!(vec![664], values(&components[4].numbers));
+        assert_eq!(vec![755, 598], values(&components[5].numbers));
+        assert_eq!(vec![114, 58], values(&orphans(&schematic)));
+    }
+
+    #[test]
+    fn linked_symbols() {
+        let input = "1.....
+                     .*#...
+                     ...2.3";
+        let schematic = parse(input, &Rules::default()).unwrap();
+
+        let components = components(&schematic).unwrap();
+
+        assert_eq!(1, components.len());
+        assert_eq!(vec![0, 1], components[0].symbols);
+        assert_eq!(3, components[0].total);
+        assert_eq!(Point { x: 3, y: 2 }, components[0].max);
+        assert_eq!(vec![2], orphans(&schematic));
+    }
+}
@@ -0,0 +1,357 @@
+use std::ops::{Index, IndexMut};
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+pub struct Point {
+    pub x: usize,
+    pub y: usize,
+}
+
+#[allow(dead_code)]
+const NEIGHBORS4: [(isize, isize); 4] = [(0, -1), (-1, 0), (1, 0), (0, 1)];
+const NEIGHBORS8: [(isize, isize); 8] = [
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+    (-1, 0),
+    (1, 0),
+    (-1, 1),
+    (0, 1),
+    (1, 1),
+];
+
+/// Row-major 2D grid.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Grid<T> {
+    width: usize,
+    height: usize,
+    cells: Vec<T>,
+}
+
+impl<T: Clone> Grid<T> {
+    pub fn new(width: usize, height: usize, value: T) -> Grid<T> {
+        return Grid {
+            width,
+            height,
+            cells: vec![value; width * height],
+        };
+    }
+}
+
+impl<T: Clone> Grid<T> {
+    /// Like `parse_with`, but rows shorter than the longest one are filled up with `fill`
+    /// on the right.
+    pub fn parse_padded_with(input: &str, fill: T, f: impl Fn(char) -> T) -> Grid<T> {
+        let width = input
+            .lines()
+            .map(|line| line.trim().chars().count())
+            .max()
+            .unwrap_or(0);
+        let mut height = 0;
+        let mut cells = Vec::new();
+        for line in input.lines() {
+            let start = cells.len();
+            cells.extend(line.trim().chars().map(&f));
+            cells.resize(start + width, fill.clone());
+            height += 1;
+        }
+        return Grid {
+            width,
+            height,
+            cells,
+        };
+    }
+}
+
+impl<T> Grid<T> {
+    /// Parses char art, one row per line. Leading and trailing whitespace of each line
+    /// is ignored, and all rows must have the same length.
+    pub fn parse_with(input: &str, f: impl Fn(char) -> T) -> Result<Grid<T>, String> {
+        let mut width = None;
+        let mut height = 0;
+        let mut cells = Vec::new();
+        for line in input.lines() {
+            let line = line.trim();
+            let start = cells.len();
+            cells.extend(line.chars().map(&f));
+            let row_width = cells.len() - start;
+            if *width.get_or_insert(row_width) != row_width {
+                return Err(format!(
+                    "row {} has {} cells, expected {}",
+                    height + 1,
+                    row_width,
+                    width.unwrap()
+                ));
+            }
+            height += 1;
+        }
+        return Ok(Grid {
+            width: width.unwrap_or(0),
+            height,
+            cells,
+        });
+    }
+
+    pub fn width(&self) -> usize {
+        return self.width;
+    }
+
+    pub fn height(&self) -> usize {
+        return self.height;
+    }
+
+    pub fn contains(&self, p: Point) -> bool {
+        return p.x < self.width && p.y < self.height;
+    }
+
+    pub fn get(&self, p: Point) -> Option<&T> {
+        if !self.contains(p) {
+            return None;
+        }
+        return Some(&self.cells[p.y * self.width + p.x]);
+    }
+
+    #[allow(dead_code)]
+    pub fn get_mut(&mut self, p: Point) -> Option<&mut T> {
+        if !self.contains(p) {
+            return None;
+        }
+        return Some(&mut self.cells[p.y * self.width + p.x]);
+    }
+
+    /// The point at the given offset from `p`, if it is inside the grid.
+    pub fn offset(&self, p: Point, dx: isize, dy: isize) -> Option<Point> {
+        let x = p.x.checked_add_signed(dx)?;
+        let y = p.y.checked_add_signed(dy)?;
+        let result = Point { x, y };
+        return if self.contains(result) {
+            Some(result)
+        } else {
+            None
+        };
+    }
+
+    /// The point at the given offset from `p`, wrapping around the edges as if the grid
+    /// was a torus.
+    pub fn offset_wrapping(&self, p: Point, dx: isize, dy: isize) -> Point {
+        let x = (p.x as isize + dx).rem_euclid(self.width as isize);
+        let y = (p.y as isize + dy).rem_euclid(self.height as isize);
+        return Point {
+            x: x as usize,
+            y: y as usize,
+        };
+    }
+
+    /// Horizontally and vertically adjacent points inside the grid.
+    #[allow(dead_code)]
+    pub fn neighbors4(&self, p: Point) -> impl Iterator<Item = Point> + '_ {
+        return NEIGHBORS4
+            .iter()
+            .filter_map(move |(dx, dy)| self.offset(p, *dx, *dy));
+    }
+
+    /// Horizontally, vertically and diagonally adjacent points inside the grid.
+    #[allow(dead_code)]
+    pub fn neighbors8(&self, p: Point) -> impl Iterator<Item = Point> + '_ {
+        return NEIGHBORS8
+            .iter()
+            .filter_map(move |(dx, dy)| self.offset(p, *dx, *dy));
+    }
+
+    /// Like `neighbors4`, but wrapping around the edges.
+    #[allow(dead_code)]
+    pub fn neighbors4_wrapping(&self, p: Point) -> impl Iterator<Item = Point> + '_ {
+        return NEIGHBORS4
+            .iter()
+            .map(move |(dx, dy)| self.offset_wrapping(p, *dx, *dy));
+    }
+
+    /// Like `neighbors8`, but wrapping around the edges.
+    #[allow(dead_code)]
+    pub fn neighbors8_wrapping(&self, p: Point) -> impl Iterator<Item = Point> + '_ {
+        return NEIGHBORS8
+            .iter()
+            .map(move |(dx, dy)| self.offset_wrapping(p, *dx, *dy));
+    }
+
+    /// Either `neighbors8_wrapping` or `neighbors8`.
+    pub fn neighbors8_with(&self, p: Point, wrapping: bool) -> impl Iterator<Item = Point> + '_ {
+        return NEIGHBORS8.iter().filter_map(move |(dx, dy)| {
+            if wrapping {
+                Some(self.offset_wrapping(p, *dx, *dy))
+            } else {
+                self.offset(p, *dx, *dy)
+            }
+        });
+    }
+
+    /// Points from `start` (inclusive) in the given direction until leaving the grid.
+    #[allow(dead_code)]
+    pub fn ray(&self, start: Point, dx: isize, dy: isize) -> impl Iterator<Item = Point> + '_ {
+        let first = if self.contains(start) {
+            Some(start)
+        } else {
+            None
+        };
+        return std::iter::successors(first, move |p| self.offset(*p, dx, dy));
+    }
+
+    /// Every point, in row-major order.
+    pub fn points(&self) -> impl Iterator<Item = Point> {
+        let width = self.width;
+        return (0..self.cells.len()).map(move |i| Point {
+            x: i % width,
+            y: i / width,
+        });
+    }
+
+    #[allow(dead_code)]
+    pub fn iter(&self) -> impl Iterator<Item = (Point, &T)> {
+        return self.points().zip(self.cells.iter());
+    }
+
+    pub fn row(&self, y: usize) -> &[T] {
+        return &self.cells[y * self.width..(y + 1) * self.width];
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = &[T]> {
+        return self.cells.chunks(self.width.max(1));
+    }
+
+    #[allow(dead_code)]
+    pub fn column(&self, x: usize) -> impl Iterator<Item = &T> {
+        return self.ray(Point { x, y: 0 }, 0, 1).map(|p| &self[p]);
+    }
+
+    /// Cells from `start` going down and to the right.
+    #[allow(dead_code)]
+    pub fn diagonal(&self, start: Point) -> impl Iterator<Item = &T> {
+        return self.ray(start, 1, 1).map(|p| &self[p]);
+    }
+
+    /// Cells from `start` going down and to the left.
+    #[allow(dead_code)]
+    pub fn anti_diagonal(&self, start: Point) -> impl Iterator<Item = &T> {
+        return self.ray(start, -1, 1).map(|p| &self[p]);
+    }
+}
+
+impl Grid<char> {
+    pub fn parse(input: &str) -> Result<Grid<char>, String> {
+        return Grid::parse_with(input, |ch| ch);
+    }
+
+    pub fn parse_padded(input: &str, fill: char) -> Grid<char> {
+        return Grid::parse_padded_with(input, fill, |ch| ch);
+    }
+}
+
+impl<T> Index<Point> for Grid<T> {
+    type Output = T;
+
+    fn index(&self, p: Point) -> &T {
+        assert!(self.contains(p), "{p:?} out of bounds");
+        return &self.cells[p.y * self.width + p.x];
+    }
+}
+
+impl<T> IndexMut<Point> for Grid<T> {
+    fn index_mut(&mut self, p: Point) -> &mut T {
+        assert!(self.contains(p), "{p:?} out of bounds");
+        return &mut self.cells[p.y * self.width + p.x];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ART: &str = "abc
+                       def
+                       ghi";
+
+    #[test]
+    fn access() {
+        let grid = Grid::parse(ART).unwrap();
+
+        assert_eq!(3, grid.width());
+        assert_eq!(3, grid.height());
+        assert_eq!(Some(&'f'), grid.get(Point { x: 2, y: 1 }));
+        assert_eq!(None, grid.get(Point { x: 3, y: 1 }));
+        assert_eq!(None, grid.offset(Point { x: 0, y: 0 }, -1, 0));
+        assert!(Grid::parse("ab\nc").is_err());
+        let padded = Grid::parse_padded("ab\nc\n\nd", '.');
+        assert_eq!(2, padded.width());
+        assert_eq!(
+            "ab|c.|..|d.",
+            padded
+                .rows()
+                .map(|it| it.iter().collect::<String>())
+                .collect::<Vec<String>>()
+                .join("|")
+        );
+    }
+
+    #[test]
+    fn neighbors() {
+        let grid = Grid::parse(ART).unwrap();
+        let chars = |points: Vec<Point>| points.iter().map(|p| grid[*p]).collect::<String>();
+
+        assert_eq!("bd", chars(grid.neighbors4(Point { x: 0, y: 0 }).collect()));
+        assert_eq!(
+            "bdfh",
+            chars(grid.neighbors4(Point { x: 1, y: 1 }).collect())
+        );
+        assert_eq!(
+            "bde",
+            chars(grid.neighbors8(Point { x: 0, y: 0 }).collect())
+        );
+        assert_eq!(
+            "abcdfghi",
+            chars(grid.neighbors8(Point { x: 1, y: 1 }).collect())
+        );
+    }
+
+    #[test]
+    fn wrapping() {
+        let grid = Grid::parse(ART).unwrap();
+        let chars = |points: Vec<Point>| points.iter().map(|p| grid[*p]).collect::<String>();
+
+        assert_eq!(
+            Point { x: 2, y: 0 },
+            grid.offset_wrapping(Point { x: 0, y: 0 }, -1, 0)
+        );
+        assert_eq!(
+            Point { x: 0, y: 1 },
+            grid.offset_wrapping(Point { x: 2, y: 2 }, 4, 2)
+        );
+        assert_eq!(
+            "gcbd",
+            chars(grid.neighbors4_wrapping(Point { x: 0, y: 0 }).collect())
+        );
+        assert_eq!(
+            "ighcbfde",
+            chars(grid.neighbors8_wrapping(Point { x: 0, y: 0 }).collect())
+        );
+    }
+
+    #[test]
+    fn views() {
+        let grid = Grid::parse(ART).unwrap();
+
+        assert_eq!(&['d', 'e', 'f'], grid.row(1));
+        assert_eq!("beh", grid.column(1).collect::<String>());
+        assert_eq!(
+            "aei",
+            grid.diagonal(Point { x: 0, y: 0 }).collect::<String>()
+        );
+        assert_eq!(
+            "bf",
+            grid.diagonal(Point { x: 1, y: 0 }).collect::<String>()
+        );
+        assert_eq!(
+            "ceg",
+            grid.anti_diagonal(Point { x: 2, y: 0 }).collect::<String>()
+        );
+        assert_eq!(3, grid.rows().count());
+    }
+}
@@ -0,0 +1,461 @@
+use std::collections::HashMap;
+
+use crate::grid::{Grid, Point};
+use crate::rules::Rules;
+
+/// A number in an editable schematic, always a single run of digits within one row.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Span {
+    pub value: i64,
+    pub y: usize,
+    pub start: usize,
+    /// Last column of the number, inclusive.
+    pub end: usize,
+}
+
+/// The effect of a single edit.
+#[derive(Default, PartialEq, Eq, Debug)]
+pub struct Change {
+    pub part_sum: i64,
+    pub group_sum: i64,
+    /// Numbers that no longer exist in this form, e.g. because a digit was added.
+    pub removed: Vec<Span>,
+    pub added: Vec<Span>,
+}
+
+/// A schematic that keeps the part number and group sums up to date while it is edited.
+///
+/// An edit only revisits the numbers on the edited row that touch the cell, the numbers
+/// around the cell and the group symbols around those, instead of the whole schematic.
+pub struct Editor {
+    rules: Rules,
+    chars: Grid<char>,
+    /// Id of the number covering each cell.
+    cells: Grid<Option<u32>>,
+    numbers: HashMap<u32, Span>,
+    next_id: u32,
+    part_sum: i64,
+    group_sum: i64,
+}
+
+/// An edit that has been worked out but not applied yet: `ch` goes to `p`, and the numbers
+/// in columns `start..=end` of its row become `spans`.
+struct Edit {
+    p: Point,
+    ch: char,
+    start: usize,
+    end: usize,
+    spans: Vec<Span>,
+}
+
+/// A number of the editor, or one of the spans a pending edit adds.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Number {
+    Id(u32),
+    Added(usize),
+}
+
+/// Adds up the values, stopping at the first error or when `what` overflows.
+fn checked_sum(
+    values: impl Iterator<Item = Result<i64, String>>,
+    what: &str,
+) -> Result<i64, String> {
+    let mut sum: i64 = 0;
+    for value in values {
+        sum = sum
+            .checked_add(value?)
+            .ok_or_else(|| format!("{what} overflows"))?;
+    }
+    return Ok(sum);
+}
+
+impl Editor {
+    /// Signed numbers and line continuation are not supported, since a single edit can
+    /// then change numbers arbitrarily far away.
+    pub fn new(input: &str, rules: &Rules) -> Result<Editor, String> {
+        if rules.signed_numbers || rules.line_continuation {
+            return Err("incremental editing needs unsigned, single-line numbers".to_string());
+        }
+        let chars = rules.parse_grid(input)?;
+        let mut editor = Editor {
+            rules: rules.clone(),
+            cells: Grid::new(chars.width(), chars.height(), None),
+            chars,
+            numbers: HashMap::new(),
+            next_id: 0,
+            part_sum: 0,
+            group_sum: 0,
+        };
+        let mut ids = Vec::new();
+        for y in 0..editor.chars.height() {
+            let row = editor.chars.row(y);
+            for span in find_numbers(row, y, 0, row.len().saturating_sub(1))? {
+                ids.push(editor.insert(span));
+            }
+        }
+        let parts = ids
+            .iter()
+            .map(|id| Ok(editor.part_value(Number::Id(*id), None)));
+        editor.part_sum = checked_sum(parts, "part sum")?;
+        let groups = editor.chars.points().map(|p| editor.group_value(p, None));
+        editor.group_sum = checked_sum(groups, "group sum")?;
+        return Ok(editor);
+    }
+
+    pub fn part_sum(&self) -> i64 {
+        return self.part_sum;
+    }
+
+    pub fn group_sum(&self) -> i64 {
+        return self.group_sum;
+    }
+
+    pub fn get(&self, p: Point) -> Option<char> {
+        return self.chars.get(p).copied();
+    }
+
+    /// The schematic as text, one line per row.
+    pub fn text(&self) -> String {
+        let rows: Vec<String> = self.chars.rows().map(|it| it.iter().collect()).collect();
+        return rows.join("\n");
+    }
+
+    /// Replaces the character at `p` and updates the sums. On error nothing changes.
+    pub fn set(&mut self, p: Point, ch: char) -> Result<Change, String> {
+        let Some(old) = self.get(p) else {
+            return Err(format!("{p:?} is outside the schematic"));
+        };
+        if old == ch {
+            return Ok(Change::default());
+        }
+        if ch.is_whitespace() {
+            return Err("cannot place whitespace in a schematic".to_string());
+        }
+
+        // Digits to the left and right may join or split numbers, so rescan the whole
+        // run of numbers touching the cell on its row.
+        let row_neighbors = [p.x.checked_sub(1), Some(p.x), p.x.checked_add(1)];
+        let mut start = p.x;
+        let mut end = p.x;
+        let mut removed_ids = Vec::new();
+        for x in row_neighbors.iter().flatten() {
+            if let Some(Some(id)) = self.cells.get(Point { x: *x, y: p.y }) {
+                let span = self.numbers[id];
+                start = start.min(span.start);
+                end = end.max(span.end);
+                if !removed_ids.contains(id) {
+                    removed_ids.push(*id);
+                }
+            }
+        }
+
+        let mut row = self.chars.row(p.y).to_vec();
+        row[p.x] = ch;
+        let edit = Edit {
+            p,
+            ch,
+            start,
+            end,
+            spans: find_numbers(&row, p.y, start, end)?,
+        };
+
+        // Groups can only change around the rescanned numbers.
+        let mut group_cells = Vec::new();
+        for x in start..=end {
+            let q = Point { x, y: p.y };
+            group_cells.push(q);
+            group_cells.extend(self.neighbors(q));
+        }
+        group_cells.sort();
+        group_cells.dedup();
+
+        // Parts can only change for the rescanned numbers and the ones around the cell,
+        // which might have gained or lost their symbol.
+        let mut old_numbers: Vec<Number> = removed_ids.iter().map(|it| Number::Id(*it)).collect();
+        let mut new_numbers: Vec<Number> = (0..edit.spans.len()).map(Number::Added).collect();
+        for number in self.adjacent_numbers(p, None) {
+            if !old_numbers.contains(&number) {
+                old_numbers.push(number);
+            }
+        }
+        for number in self.adjacent_numbers(p, Some(&edit)) {
+            if !new_numbers.contains(&number) {
+                new_numbers.push(number);
+            }
+        }
+
+        // Work out every sum before touching the schematic, so an overflow leaves it as
+        // it was.
+        let part = |numbers: &[Number], edit: Option<&Edit>| {
+            let values = numbers.iter().map(|it| Ok(self.part_value(*it, edit)));
+            return checked_sum(values, "part sum");
+        };
+        let group = |edit: Option<&Edit>| {
+            let values = group_cells.iter().map(|it| self.group_value(*it, edit));
+            return checked_sum(values, "group sum");
+        };
+        let (old_part, new_part) = (part(&old_numbers, None)?, part(&new_numbers, Some(&edit))?);
+        let (old_group, new_group) = (group(None)?, group(Some(&edit))?);
+        let overflow = |what: &str| format!("{what} overflows");
+        let mut change = Change {
+            part_sum: new_part
+                .checked_sub(old_part)
+                .ok_or_else(|| overflow("part sum"))?,
+            group_sum: new_group
+                .checked_sub(old_group)
+                .ok_or_else(|| overflow("group sum"))?,
+            ..Change::default()
+        };
+        let part_sum = self
+            .part_sum
+            .checked_add(change.part_sum)
+            .ok_or_else(|| overflow("part sum"))?;
+        let group_sum = self
+            .group_sum
+            .checked_add(change.group_sum)
+            .ok_or_else(|| overflow("group sum"))?;
+
+        for id in removed_ids {
+            let span = self.numbers.remove(&id).unwrap();
+            for x in span.start..=span.end {
+                self.cells[Point { x, y: span.y }] = None;
+            }
+            change.removed.push(span);
+        }
+        self.chars[p] = ch;
+        for span in edit.spans {
+            self.insert(span);
+            change.added.push(span);
+        }
+        self.part_sum = part_sum;
+        self.group_sum = group_sum;
+        return Ok(change);
+    }
+
+    fn neighbors(&self, p: Point) -> impl Iterator<Item = Point> + '_ {
+        return self.chars.neighbors8_with(p, self.rules.toroidal);
+    }
+
+    fn char_at(&self, p: Point, edit: Option<&Edit>) -> char {
+        return match edit {
+            Some(edit) if edit.p == p => edit.ch,
+            _ => self.chars[p],
+        };
+    }
+
+    fn number_at(&self, p: Point, edit: Option<&Edit>) -> Option<Number> {
+        return match edit {
+            Some(edit) if p.y == edit.p.y && (edit.start..=edit.end).contains(&p.x) => edit
+                .spans
+                .iter()
+                .position(|it| (it.start..=it.end).contains(&p.x))
+                .map(Number::Added),
+            _ => self.cells[p].map(Number::Id),
+        };
+    }
+
+    fn span(&self, number: Number, edit: Option<&Edit>) -> Span {
+        return match number {
+            Number::Id(id) => self.numbers[&id],
+            Number::Added(index) => edit.unwrap().spans[index],
+        };
+    }
+
+    fn adjacent_numbers(&self, p: Point, edit: Option<&Edit>) -> Vec<Number> {
+        let mut result = Vec::new();
+        for q in self.neighbors(p) {
+            if let Some(number) = self.number_at(q, edit) {
+                if !result.contains(&number) {
+                    result.push(number);
+                }
+            }
+        }
+        return result;
+    }
+
+    fn is_symbol(&self, p: Point, edit: Option<&Edit>) -> bool {
+        return self.rules.is_symbol(self.char_at(p, edit));
+    }
+
+    /// The number's value if it is a part number, zero otherwise, as it is or would be
+    /// after `edit`.
+    fn part_value(&self, number: Number, edit: Option<&Edit>) -> i64 {
+        let span = self.span(number, edit);
+        for x in span.start..=span.end {
+            if self
+                .neighbors(Point { x, y: span.y })
+                .any(|q| self.is_symbol(q, edit))
+            {
+                return span.value;
+            }
+        }
+        return 0;
+    }
+
+    /// What the symbol at `p` adds to the group sum, zero if it is not a group symbol.
+    fn group_value(&self, p: Point, edit: Option<&Edit>) -> Result<i64, String> {
+        if !self.is_symbol(p, edit) {
+            return Ok(0);
+        }
+        let overflow = || format!("group at {p:?} overflows");
+        let mut sum: i64 = 0;
+        for group in self.rules.groups.iter() {
+            if !group.symbols.contains(self.char_at(p, edit)) {
+                continue;
+            }
+            let adjacent = self.adjacent_numbers(p, edit);
+            if adjacent.len() == group.count {
+                let values = adjacent.iter().map(|it| self.span(*it, edit).value);
+                let value = group.combine(values).ok_or_else(overflow)?;
+                sum = sum.checked_add(value).ok_or_else(overflow)?;
+            }
+        }
+        return Ok(sum);
+    }
+
+    fn insert(&mut self, span: Span) -> u32 {
+        let id = self.next_id;
+        self.next_id += 1;
+        for x in span.start..=span.end {
+            self.cells[Point { x, y: span.y }] = Some(id);
+        }
+        self.numbers.insert(id, span);
+        return id;
+    }
+}
+
+/// The numbers starting in the given columns of a row.
+fn find_numbers(row: &[char], y: usize, start: usize, end: usize) -> Result<Vec<Span>, String> {
+    let mut result = Vec::new();
+    let mut x = start;
+    while x <= end && x < row.len() {
+        if !row[x].is_ascii_digit() {
+            x += 1;
+            continue;
+        }
+        let mut span = Span {
+            value: 0,
+            y,
+            start: x,
+            end: x,
+        };
+        while let Some(digit) = row.get(x).and_then(|it| it.to_digit(10)) {
+            span.value = span
+                .value
+                .checked_mul(10)
+                .and_then(|it| it.checked_add(digit as i64))
+                .ok_or_else(|| format!("number at {:?} is too large", (span.start, y)))?;
+            span.end = x;
+            x += 1;
+        }
+        result.push(span);
+    }
+    return Ok(result);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse, solve, solve2};
+
+    const INPUT: &str = "467..114..
+                         ...*......
+                         ..35..633.
+                         ......#...
+                         617*......
+                         .....+.58.
+                         ..592.....
+                         ......755.
+                         ...$.*....
+                         .664.598..";
+
+    #[test]
+    fn edits() {
+        let mut editor = Editor::new(INPUT, &Rules::default()).unwrap();
+        let values = |spans: &[Span]| spans.iter().map(|it| it.value).collect::<Vec<i64>>();
+        assert_eq!(4361, editor.part_sum());
+        assert_eq!(467835, editor.group_sum());
+
+        // Join 664 and 598 into one number.
+        let change = editor.set(Point { x: 4, y: 9 }, '0').unwrap();
+        assert_eq!(6640598 - 664 - 598, change.part_sum);
+        assert_eq!(755 * (6640598 - 598), change.group_sum);
+        assert_eq!(vec![664, 598], values(&change.removed));
+        assert_eq!(vec![6640598], values(&change.added));
+
+        // Remove the only symbol next to 633.
+        let change = editor.set(Point { x: 6, y: 3 }, '.').unwrap();
+        assert_eq!(-633, change.part_sum);
+        assert_eq!(0, change.group_sum);
+        assert!(change.removed.is_empty() && change.added.is_empty());
+
+        // Grow 58 into a part, then make its symbol a gear.
+        let change = editor.set(Point { x: 6, y: 5 }, '1').unwrap();
+        assert_eq!(158, change.part_sum);
+        assert_eq!(vec![58], values(&change.removed));
+        let change = editor.set(Point { x: 5, y: 5 }, '*').unwrap();
+        assert_eq!(0, change.part_sum);
+        assert_eq!(592 * 158, change.group_sum);
+
+        let schematic = parse(&editor.text(), &Rules::default()).unwrap();
+        assert_eq!(solve(&schematic).unwrap(), editor.part_sum());
+        assert_eq!(
+            solve2(&schematic, &Rules::default()).unwrap(),
+            editor.group_sum()
+        );
+        assert_eq!(
+            Change::default(),
+            editor.set(Point { x: 0, y: 0 }, '4').unwrap()
+        );
+        assert!(editor.set(Point { x: 10, y: 0 }, '4').is_err());
+    }
+
+    #[test]
+    fn overflow_leaves_state_intact() {
+        let mut editor = Editor::new("999999999.999999999*", &Rules::default()).unwrap();
+
+        assert!(editor.set(Point { x: 9, y: 0 }, '9').is_err());
+        assert_eq!(Some('.'), editor.get(Point { x: 9, y: 0 }));
+        assert_eq!(999999999, editor.part_sum());
+
+        // 3037000500 squared is just above i64::MAX.
+        assert!(Editor::new("3037000500*3037000500", &Rules::default()).is_err());
+        let input = "3037000500*.037000500";
+        let mut editor = Editor::new(input, &Rules::default()).unwrap();
+        assert!(editor.set(Point { x: 11, y: 0 }, '3').is_err());
+        assert_eq!(input, editor.text());
+        assert_eq!((3037000500, 0), (editor.part_sum(), editor.group_sum()));
+        let change = editor.set(Point { x: 11, y: 0 }, '1').unwrap();
+        assert_eq!(1037000500, change.part_sum);
+        assert_eq!(3037000500 * 1037000500, editor.group_sum());
+    }
+
+    #[test]
+    fn matches_full_recompute() {
+        let chars = ['.', '.', '.', '1', '2', '5', '9', '*', '#'];
+        for rules in [
+            Rules::default(),
+            Rules {
+                toroidal: true,
+                ..Rules::default()
+            },
+        ] {
+            let mut editor = Editor::new(INPUT, &rules).unwrap();
+            let mut seed: u64 = 12345;
+            for _ in 0..500 {
+                seed = seed
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                let x = (seed >> 33) as usize % 10;
+                let y = (seed >> 45) as usize % 10;
+                let ch = chars[(seed >> 20) as usize % chars.len()];
+
+                editor.set(Point { x, y }, ch).unwrap();
+
+                let schematic = parse(&editor.text(), &rules).unwrap();
+                assert_eq!(solve(&schematic).unwrap(), editor.part_sum());
+                assert_eq!(solve2(&schematic, &rules).unwrap(), editor.group_sum());
+            }
+        }
+    }
+}
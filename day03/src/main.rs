@@ -1,11 +1,18 @@
-#[derive(Clone, Copy)]
-struct Point {
-    x: u32,
-    y: u32,
-}
+#![allow(clippy::needless_return)]
+
+mod components;
+mod grid;
+mod incremental;
+mod render;
+mod rules;
+
+use std::path::PathBuf;
+
+use grid::{Grid, Point};
+use rules::{GroupRule, Rules};
 
 struct Number {
-    value: u32,
+    value: i64,
     positions: Vec<Point>,
 }
 
@@ -14,105 +21,319 @@ struct Symbol {
     position: Point,
 }
 
-fn is_adjacent(p1: Point, p2: Point) -> bool {
-    let dx = (p1.x).abs_diff(p2.x);
-    let dy = (p1.y).abs_diff(p2.y);
-    return dx <= 1 && dy <= 1;
+/// What covers a cell of the schematic, as an index into the number or symbol list.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Occupant {
+    Empty,
+    Number(u32),
+    Symbol(u32),
 }
 
-fn parse(input: &str) -> (Vec<Number>, Vec<Symbol>) {
-    let mut numbers: Vec<Number> = Vec::new();
-    let mut symbols: Vec<Symbol> = Vec::new();
-    let mut position = Point { x: 0, y: 0 };
-    for line in input.lines() {
-        let mut number: Number = Number {
-            value: 0,
-            positions: Vec::new(),
-        };
-        for ch in line.trim().chars() {
-            if ch.is_numeric() {
-                let val: u32 = format!("{ch}").parse().unwrap();
-                number.value = number.value * 10 + val;
-                number.positions.push(position);
-            } else {
-                if !number.positions.is_empty() {
-                    numbers.push(number);
-                    number = Number {
-                        value: 0,
-                        positions: Vec::new(),
-                    };
-                }
-                if ch != '.' {
-                    symbols.push(Symbol {
-                        value: ch,
-                        position: position.clone(),
-                    });
+struct Schematic {
+    numbers: Vec<Number>,
+    symbols: Vec<Symbol>,
+    cells: Grid<Occupant>,
+    toroidal: bool,
+}
+
+impl Schematic {
+    fn neighbors(&self, position: Point) -> impl Iterator<Item = Point> + '_ {
+        return self.cells.neighbors8_with(position, self.toroidal);
+    }
+
+    /// Indices of the distinct numbers touching the given cell.
+    fn adjacent_numbers(&self, position: Point) -> Vec<usize> {
+        let mut result = Vec::new();
+        for neighbor in self.neighbors(position) {
+            if let Occupant::Number(index) = self.cells[neighbor] {
+                if !result.contains(&(index as usize)) {
+                    result.push(index as usize);
                 }
             }
-            position.x += 1;
         }
-        if !number.positions.is_empty() {
-            numbers.push(number);
-        }
-        position.x = 0;
-        position.y += 1;
+        return result;
     }
-    return (numbers, symbols);
-}
 
-fn solve(input: &str) -> u32 {
-    let (numbers, symbols) = parse(input);
+    /// For each number, whether it touches a symbol.
+    fn part_numbers(&self) -> Vec<bool> {
+        let mut result = vec![false; self.numbers.len()];
+        for symbol in self.symbols.iter() {
+            for index in self.adjacent_numbers(symbol.position) {
+                result[index] = true;
+            }
+        }
+        return result;
+    }
 
-    let mut sum = 0;
-    'outer: for number in numbers {
-        for position in number.positions {
-            for symbol in symbols.iter() {
-                if is_adjacent(position, symbol.position) {
-                    sum += number.value;
-                    continue 'outer;
+    /// Sum over all symbols matching a group rule of their combined adjacent numbers.
+    fn sum_groups(&self, rules: &Rules) -> Result<i64, String> {
+        let mut sum: i64 = 0;
+        for symbol in self.symbols.iter() {
+            for group in rules.groups.iter() {
+                if !group.symbols.contains(symbol.value) {
+                    continue;
+                }
+                let adjacent = self.adjacent_numbers(symbol.position);
+                if adjacent.len() == group.count {
+                    let value = group
+                        .combine(adjacent.iter().map(|it| self.numbers[*it].value))
+                        .ok_or_else(|| format!("group at {:?} overflows", symbol.position))?;
+                    sum = sum
+                        .checked_add(value)
+                        .ok_or_else(|| "group sum overflows".to_string())?;
                 }
             }
         }
+        return Ok(sum);
     }
+}
 
-    return sum;
+fn finish_number(number: &mut Number, negative: &mut bool, numbers: &mut Vec<Number>) {
+    if number.positions.is_empty() {
+        return;
+    }
+    let mut number = std::mem::replace(
+        number,
+        Number {
+            value: 0,
+            positions: Vec::new(),
+        },
+    );
+    if *negative {
+        number.value = -number.value;
+        *negative = false;
+    }
+    numbers.push(number);
 }
 
-fn solve2(input: &str) -> u32 {
-    let (numbers, symbols) = parse(input);
+fn parse(input: &str, rules: &Rules) -> Result<Schematic, String> {
+    let grid = rules.parse_grid(input)?;
+    let mut numbers: Vec<Number> = Vec::new();
+    let mut symbols: Vec<Symbol> = Vec::new();
+    let mut cells = Grid::new(grid.width(), grid.height(), Occupant::Empty);
+    let mut number: Number = Number {
+        value: 0,
+        positions: Vec::new(),
+    };
+    let mut negative = false;
+    for (y, row) in grid.rows().enumerate() {
+        for (x, ch) in row.iter().enumerate() {
+            let position = Point { x, y };
+            if let Some(val) = ch.to_digit(10) {
+                number.positions.push(position);
+                number.value = number
+                    .value
+                    .checked_mul(10)
+                    .and_then(|it| it.checked_add(val as i64))
+                    .ok_or_else(|| format!("number at {:?} is too large", number.positions[0]))?;
+                cells[position] = Occupant::Number(u32::try_from(numbers.len()).unwrap());
+                continue;
+            }
 
-    let mut sum = 0;
-    for symbol in symbols {
-        if symbol.value != '*' {
-            continue;
+            finish_number(&mut number, &mut negative, &mut numbers);
+            let is_sign = rules.signed_numbers
+                && *ch == '-'
+                && row.get(x + 1).is_some_and(|it| it.is_ascii_digit());
+            if is_sign {
+                negative = true;
+                number.positions.push(position);
+                cells[position] = Occupant::Number(u32::try_from(numbers.len()).unwrap());
+            } else if rules.is_symbol(*ch) {
+                cells[position] = Occupant::Symbol(u32::try_from(symbols.len()).unwrap());
+                symbols.push(Symbol {
+                    value: *ch,
+                    position,
+                });
+            }
         }
+        let continues = rules.line_continuation
+            && !number.positions.is_empty()
+            && grid
+                .get(Point { x: 0, y: y + 1 })
+                .is_some_and(|it| it.is_ascii_digit());
+        if !continues {
+            finish_number(&mut number, &mut negative, &mut numbers);
+        }
+    }
+    finish_number(&mut number, &mut negative, &mut numbers);
+    return Ok(Schematic {
+        numbers,
+        symbols,
+        cells,
+        toroidal: rules.toroidal,
+    });
+}
 
-        let mut gear_ratio = 1;
-        let mut count = 0;
-        for number in numbers.iter() {
-            for position in number.positions.iter() {
-                if is_adjacent(*position, symbol.position) {
-                    gear_ratio *= number.value;
-                    count += 1;
-                    break;
-                }
+fn solve(schematic: &Schematic) -> Result<i64, String> {
+    let is_part = schematic.part_numbers();
+    let mut sum: i64 = 0;
+    for (number, is_part) in schematic.numbers.iter().zip(is_part) {
+        if is_part {
+            sum = sum
+                .checked_add(number.value)
+                .ok_or_else(|| "part sum overflows".to_string())?;
+        }
+    }
+
+    return Ok(sum);
+}
+
+fn solve2(schematic: &Schematic, rules: &Rules) -> Result<i64, String> {
+    return schematic.sum_groups(rules);
+}
+
+struct Args {
+    input: PathBuf,
+    rules: Rules,
+    components: bool,
+    edit: bool,
+    svg: Option<PathBuf>,
+}
+
+const USAGE: &str =
+    "usage: day03 [INPUT] [--empty CHARS] [--symbols CHARS] [--group CHARS:COUNT:product|sum]... [--signed] [--continuation] [--toroidal] [--components] [--edit] [--svg FILE]";
+
+fn parse_args(args: impl Iterator<Item = String>) -> Result<Args, String> {
+    let mut input = None;
+    let mut rules = Rules::default();
+    let mut groups = Vec::new();
+    let mut components = false;
+    let mut edit = false;
+    let mut svg = None;
+    let mut args = args;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--empty" => rules.empty = args.next().ok_or("--empty needs a value")?,
+            "--symbols" => rules.symbols = Some(args.next().ok_or("--symbols needs a value")?),
+            "--group" => {
+                let spec = args.next().ok_or("--group needs a value")?;
+                groups.push(GroupRule::parse(&spec)?);
             }
+            "--signed" => rules.signed_numbers = true,
+            "--continuation" => rules.line_continuation = true,
+            "--toroidal" => rules.toroidal = true,
+            "--components" => components = true,
+            "--edit" => edit = true,
+            "--svg" => svg = Some(PathBuf::from(args.next().ok_or("--svg needs a value")?)),
+            _ if arg.starts_with("--") => return Err(format!("unknown option '{arg}'")),
+            _ if input.is_none() => input = Some(PathBuf::from(arg)),
+            _ => return Err(format!("unexpected argument '{arg}'")),
         }
+    }
+    if !groups.is_empty() {
+        rules.groups = groups;
+    }
+    return Ok(Args {
+        input: input.unwrap_or_else(|| PathBuf::from("input.txt")),
+        rules,
+        components,
+        edit,
+        svg,
+    });
+}
+
+fn print_components(schematic: &Schematic) -> Result<(), String> {
+    for component in components::components(schematic)? {
+        let symbols: String = component
+            .symbols
+            .iter()
+            .map(|it| schematic.symbols[*it].value)
+            .collect();
+        let numbers: Vec<String> = component
+            .numbers
+            .iter()
+            .map(|it| schematic.numbers[*it].value.to_string())
+            .collect();
+        println!(
+            "({},{})-({},{}) {symbols} [{}] total {}",
+            component.min.x,
+            component.min.y,
+            component.max.x,
+            component.max.y,
+            numbers.join(", "),
+            component.total
+        );
+    }
+    let orphans: Vec<String> = components::orphans(schematic)
+        .iter()
+        .map(|it| schematic.numbers[*it].value.to_string())
+        .collect();
+    println!("orphans [{}]", orphans.join(", "));
+    return Ok(());
+}
 
-        if count == 2 {
-            sum += gear_ratio;
+/// Applies edits read from stdin, one "X Y CHAR" per line. An empty line prints the
+/// schematic.
+fn edit(input: &str, rules: &Rules) -> Result<(), String> {
+    let mut editor = incremental::Editor::new(input, rules)?;
+    println!("{} {}", editor.part_sum(), editor.group_sum());
+    for line in std::io::stdin().lines() {
+        let line = line.map_err(|err| err.to_string())?;
+        if line.trim().is_empty() {
+            println!("{}", editor.text());
+            continue;
+        }
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        let [x, y, ch] = parts[..] else {
+            eprintln!("expected X Y CHAR");
+            continue;
+        };
+        let (Ok(x), Ok(y), Some(ch)) = (x.parse(), y.parse(), ch.chars().next()) else {
+            eprintln!("expected X Y CHAR");
+            continue;
+        };
+        match editor.set(Point { x, y }, ch) {
+            Ok(change) => {
+                let removed: Vec<String> = change
+                    .removed
+                    .iter()
+                    .map(|it| it.value.to_string())
+                    .collect();
+                let added: Vec<String> =
+                    change.added.iter().map(|it| it.value.to_string()).collect();
+                println!(
+                    "{} ({:+}) {} ({:+}) removed [{}] added [{}]",
+                    editor.part_sum(),
+                    change.part_sum,
+                    editor.group_sum(),
+                    change.group_sum,
+                    removed.join(", "),
+                    added.join(", ")
+                );
+            }
+            Err(err) => eprintln!("{err}"),
         }
     }
+    return Ok(());
+}
 
-    return sum;
+fn fail<T>(err: String) -> T {
+    eprintln!("{err}");
+    std::process::exit(1);
 }
 
 fn main() {
-    let input = std::fs::read_to_string("input.txt").unwrap();
-    let output = solve(input.as_str());
+    let args = parse_args(std::env::args().skip(1)).unwrap_or_else(|err| {
+        eprintln!("{err}\n{USAGE}");
+        std::process::exit(2);
+    });
+    let input = std::fs::read_to_string(&args.input).unwrap();
+    if args.edit {
+        edit(input.as_str(), &args.rules).unwrap_or_else(|err| eprintln!("{err}"));
+        return;
+    }
+    let schematic = parse(input.as_str(), &args.rules).unwrap_or_else(fail);
+    if let Some(path) = &args.svg {
+        std::fs::write(path, render::svg(&schematic, &args.rules)).unwrap();
+    }
+    if args.components {
+        print_components(&schematic).unwrap_or_else(fail);
+    }
+
+    let output = solve(&schematic).unwrap_or_else(fail);
     println!("{output}");
 
-    let output2 = solve2(input.as_str());
+    let output2 = solve2(&schematic, &args.rules).unwrap_or_else(fail);
     println!("{output2}");
 }
 
@@ -120,6 +341,15 @@ fn main() {
 mod tests {
     use super::*;
 
+    /// Both answers, from a single parse.
+    fn answers(input: &str, rules: &Rules) -> (i64, i64) {
+        let schematic = parse(input, rules).unwrap();
+        return (
+            solve(&schematic).unwrap(),
+            solve2(&schematic, rules).unwrap(),
+        );
+    }
+
     #[test]
     fn sample() {
         let input = "467..114..
@@ -134,7 +364,8 @@ mod tests {
                      .664.598..";
         let expected_output = 4361;
 
-        let output = solve(input);
+        let schematic = parse(input, &Rules::default()).unwrap();
+        let output = solve(&schematic).unwrap();
 
         assert_eq!(expected_output, output);
     }
@@ -153,8 +384,134 @@ mod tests {
                      .664.598..";
         let expected_output = 467835;
 
-        let output = solve2(input);
+        let schematic = parse(input, &Rules::default()).unwrap();
+        let output = solve2(&schematic, &Rules::default()).unwrap();
 
         assert_eq!(expected_output, output);
     }
+
+    #[test]
+    fn custom_rules() {
+        let input = "2.3~#5
+                     .*..4.
+                     ....$6";
+        let rules = Rules {
+            empty: ".".to_string(),
+            symbols: Some("*#".to_string()),
+            groups: vec![
+                GroupRule::parse("*:2:sum").unwrap(),
+                GroupRule::parse("#:2:product").unwrap(),
+            ],
+            ..Rules::default()
+        };
+
+        // '~' and '$' are neither empty nor symbols, so they separate numbers but
+        // do not make parts.
+        assert_eq!((2 + 3 + 5 + 4, 2 + 3 + 5 * 4), answers(input, &rules));
+        assert_eq!(
+            (2 + 3 + 5 + 4 + 6, 2 * 3),
+            answers(input, &Rules::default())
+        );
+    }
+
+    #[test]
+    fn signed_numbers() {
+        let input = "-12*3.
+                     ...-..
+                     4-5..7";
+        let rules = Rules {
+            signed_numbers: true,
+            ..Rules::default()
+        };
+
+        assert_eq!((12 + 3 + 4 + 5, 12 * 3), answers(input, &Rules::default()));
+        // "4-5" reads as 4 and -5, which leaves the 4 without a symbol. The lone '-'
+        // is still a symbol.
+        assert_eq!((-12 + 3 - 5, -12 * 3), answers(input, &rules));
+    }
+
+    #[test]
+    fn line_continuation() {
+        let input = "...*12
+                     34....";
+        let rules = Rules {
+            line_continuation: true,
+            ..Rules::default()
+        };
+
+        assert_eq!(12, answers(input, &Rules::default()).0);
+        assert_eq!(1234, answers(input, &rules).0);
+    }
+
+    #[test]
+    fn toroidal() {
+        let input = "5...*
+                     .....
+                     ....7";
+        let rules = Rules {
+            toroidal: true,
+            ..Rules::default()
+        };
+
+        assert_eq!(0, answers(input, &Rules::default()).0);
+        assert_eq!((5 + 7, 5 * 7), answers(input, &rules));
+    }
+
+    #[test]
+    fn ragged_rows() {
+        let input = "467
+                     ...*
+                     ..35..6";
+        let no_empty = Rules {
+            empty: String::new(),
+            ..Rules::default()
+        };
+
+        assert_eq!((467 + 35, 467 * 35), answers(input, &Rules::default()));
+        assert!(parse(input, &no_empty).is_err());
+    }
+
+    #[test]
+    fn large_schematic() {
+        // The sample tiled 100 times in each direction, no number touches a symbol of
+        // a neighboring tile.
+        let tile = [
+            "467..114..",
+            "...*......",
+            "..35..633.",
+            "......#...",
+            "617*......",
+            ".....+.58.",
+            "..592.....",
+            "......755.",
+            "...$.*....",
+            ".664.598..",
+        ];
+        let rows: Vec<String> = (0..1000).map(|y| tile[y % 10].repeat(100)).collect();
+
+        let answers = answers(&rows.join("\n"), &Rules::default());
+
+        assert_eq!((4361 * 10000, 467835 * 10000), answers);
+    }
+
+    #[test]
+    fn large_numbers() {
+        let input = "9999999999*2";
+
+        assert_eq!(
+            (9999999999 + 2, 19999999998),
+            answers(input, &Rules::default())
+        );
+        assert!(parse("99999999999999999999*", &Rules::default()).is_err());
+
+        let schematic = parse("9999999999999999*9999999999999999", &Rules::default()).unwrap();
+        assert_eq!(Ok(19999999999999998), solve(&schematic));
+        assert_eq!(
+            Err("group at Point { x: 16, y: 0 } overflows".to_string()),
+            solve2(&schematic, &Rules::default())
+        );
+        let schematic = parse("9223372036854775807*1", &Rules::default()).unwrap();
+        assert_eq!(Err("part sum overflows".to_string()), solve(&schematic));
+        assert!(components::components(&schematic).is_err());
+    }
 }
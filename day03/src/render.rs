@@ -0,0 +1,149 @@
+use std::fmt::Write;
+
+use crate::grid::Point;
+use crate::rules::Rules;
+use crate::{Occupant, Schematic};
+
+const CELL: usize = 12;
+
+const PART: &str = "#7cc47c";
+const NON_PART: &str = "#e8a0a0";
+const SYMBOL: &str = "#9ab8e8";
+const GEAR: &str = "#f0c040";
+const EDGE: &str = "#404040";
+
+fn center(p: Point) -> (usize, usize) {
+    return (p.x * CELL + CELL / 2, p.y * CELL + CELL / 2);
+}
+
+fn escape(ch: char) -> String {
+    return match ch {
+        '&' => "&amp;".to_string(),
+        '<' => "&lt;".to_string(),
+        '>' => "&gt;".to_string(),
+        _ => ch.to_string(),
+    };
+}
+
+fn cell(out: &mut String, p: Point, fill: &str, ch: char) {
+    let (cx, cy) = center(p);
+    writeln!(
+        out,
+        r#"<rect x="{}" y="{}" width="{CELL}" height="{CELL}" fill="{fill}"/><text x="{cx}" y="{}">{}</text>"#,
+        p.x * CELL,
+        p.y * CELL,
+        cy + CELL / 3,
+        escape(ch)
+    )
+    .unwrap();
+}
+
+/// Renders the schematic as SVG: part numbers green, other numbers red, symbols blue and
+/// symbols that satisfy a group rule (gears) yellow, with a line from each symbol to every
+/// number it touches.
+pub fn svg(schematic: &Schematic, rules: &Rules) -> String {
+    let width = schematic.cells.width() * CELL;
+    let height = schematic.cells.height() * CELL;
+    let mut out = String::new();
+    writeln!(
+        out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}">"#
+    )
+    .unwrap();
+    writeln!(out, r#"<rect width="100%" height="100%" fill="white"/>"#).unwrap();
+    writeln!(
+        out,
+        r#"<g font-family="monospace" font-size="{}" text-anchor="middle">"#,
+        CELL - 2
+    )
+    .unwrap();
+
+    let is_part = schematic.part_numbers();
+    for (number, is_part) in schematic.numbers.iter().zip(is_part) {
+        let fill = if is_part { PART } else { NON_PART };
+        // Restore leading zeros, and the sign of negative numbers in its own cell.
+        let sign = if number.value < 0 { "-" } else { "" };
+        let digits = number.positions.len() - sign.len();
+        let text = format!("{sign}{:0digits$}", number.value.unsigned_abs());
+        for (position, ch) in number.positions.iter().zip(text.chars()) {
+            cell(&mut out, *position, fill, ch);
+        }
+    }
+    for symbol in schematic.symbols.iter() {
+        let adjacent = schematic.adjacent_numbers(symbol.position).len();
+        let is_gear = rules
+            .groups
+            .iter()
+            .any(|group| group.symbols.contains(symbol.value) && group.count == adjacent);
+        let fill = if is_gear { GEAR } else { SYMBOL };
+        cell(&mut out, symbol.position, fill, symbol.value);
+    }
+    writeln!(out, "</g>").unwrap();
+
+    writeln!(out, r#"<g stroke="{EDGE}" stroke-width="1">"#).unwrap();
+    for symbol in schematic.symbols.iter() {
+        let mut seen = Vec::new();
+        for neighbor in schematic.neighbors(symbol.position) {
+            let Occupant::Number(index) = schematic.cells[neighbor] else {
+                continue;
+            };
+            // Edges across the border of a toroidal schematic would cross the whole image.
+            let wraps = neighbor.x.abs_diff(symbol.position.x) > 1
+                || neighbor.y.abs_diff(symbol.position.y) > 1;
+            if seen.contains(&index) || wraps {
+                continue;
+            }
+            seen.push(index);
+            let (x1, y1) = center(symbol.position);
+            let (x2, y2) = center(neighbor);
+            writeln!(out, r#"<line x1="{x1}" y1="{y1}" x2="{x2}" y2="{y2}"/>"#).unwrap();
+        }
+    }
+    writeln!(out, "</g>").unwrap();
+    writeln!(out, "</svg>").unwrap();
+    return out;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    #[test]
+    fn colors() {
+        let input = "467..114..
+                     ...*......
+                     ..35..633.
+                     ......#...
+                     617*......
+                     .....+.58.
+                     ..592.....
+                     ......755.
+                     ...$.*....
+                     .664.598..";
+        let rules = Rules::default();
+
+        let svg = svg(&parse(input, &rules).unwrap(), &rules);
+
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.trim_end().ends_with("</svg>"));
+        // 467 + 35 + 633 + 617 + 592 + 755 + 664 + 598 are parts, 114 and 58 are not.
+        assert_eq!(23, svg.matches(PART).count());
+        assert_eq!(5, svg.matches(NON_PART).count());
+        // Two of the three '*' touch exactly two numbers.
+        assert_eq!(2, svg.matches(GEAR).count());
+        assert_eq!(4, svg.matches(SYMBOL).count());
+        assert_eq!(8, svg.matches("<line").count());
+    }
+
+    #[test]
+    fn escaping() {
+        let rules = Rules::default();
+
+        let svg = svg(&parse("01&<", &rules).unwrap(), &rules);
+
+        assert!(svg.contains(">0<"));
+        assert!(svg.contains(">&amp;<"));
+        assert!(svg.contains(">&lt;<"));
+    }
+}
@@ -0,0 +1,160 @@
+use crate::grid::Grid;
+
+/// How to combine the numbers around a group symbol.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Combine {
+    Product,
+    Sum,
+}
+
+/// Symbols that count when they touch exactly `count` numbers, like gears do.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct GroupRule {
+    pub symbols: String,
+    pub count: usize,
+    pub combine: Combine,
+}
+
+impl GroupRule {
+    pub fn gears() -> GroupRule {
+        return GroupRule {
+            symbols: "*".to_string(),
+            count: 2,
+            combine: Combine::Product,
+        };
+    }
+
+    /// Parses a rule in the form `CHARS:COUNT:product|sum`, e.g. `*:2:product`.
+    pub fn parse(spec: &str) -> Result<GroupRule, String> {
+        let parts: Vec<&str> = spec.split(":").collect();
+        let [symbols, count, combine] = parts[..] else {
+            return Err(format!("expected CHARS:COUNT:product|sum, got '{spec}'"));
+        };
+        if symbols.is_empty() {
+            return Err(format!("no symbols in '{spec}'"));
+        }
+        let count = count
+            .parse()
+            .map_err(|err| format!("invalid count in '{spec}': {err}"))?;
+        let combine = match combine {
+            "product" => Combine::Product,
+            "sum" => Combine::Sum,
+            _ => return Err(format!("unknown combination '{combine}' in '{spec}'")),
+        };
+        return Ok(GroupRule {
+            symbols: symbols.to_string(),
+            count,
+            combine,
+        });
+    }
+
+    /// Combined value of the numbers, `None` on overflow.
+    pub fn combine(&self, values: impl Iterator<Item = i64>) -> Option<i64> {
+        let mut values = values;
+        return match self.combine {
+            Combine::Product => values.try_fold(1i64, |a, b| a.checked_mul(b)),
+            Combine::Sum => values.try_fold(0i64, |a, b| a.checked_add(b)),
+        };
+    }
+}
+
+/// How to read a schematic: which characters are symbols, which symbols form groups,
+/// and how numbers and adjacency work.
+///
+/// Digits are always numbers. Characters that are neither empty nor symbols are ignored.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Rules {
+    pub empty: String,
+    /// Symbol characters, or `None` for everything that is not a digit or empty.
+    pub symbols: Option<String>,
+    pub groups: Vec<GroupRule>,
+    /// Whether a '-' directly in front of a number is its sign rather than a symbol.
+    pub signed_numbers: bool,
+    /// Whether a number reaching the end of a line continues at the start of the next.
+    pub line_continuation: bool,
+    /// Whether opposite edges of the schematic touch.
+    pub toroidal: bool,
+}
+
+impl Default for Rules {
+    fn default() -> Rules {
+        return Rules {
+            empty: ".".to_string(),
+            symbols: None,
+            groups: vec![GroupRule::gears()],
+            signed_numbers: false,
+            line_continuation: false,
+            toroidal: false,
+        };
+    }
+}
+
+impl Rules {
+    /// Reads the schematic, filling up short rows with the first empty character. Without
+    /// one, all rows must have the same length.
+    pub fn parse_grid(&self, input: &str) -> Result<Grid<char>, String> {
+        return match self.empty.chars().next() {
+            Some(fill) => Ok(Grid::parse_padded(input, fill)),
+            None => Grid::parse(input),
+        };
+    }
+
+    pub fn is_symbol(&self, ch: char) -> bool {
+        if ch.is_ascii_digit() || self.empty.contains(ch) {
+            return false;
+        }
+        return match &self.symbols {
+            Some(symbols) => symbols.contains(ch),
+            None => true,
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn group_spec() {
+        assert_eq!(GroupRule::gears(), GroupRule::parse("*:2:product").unwrap());
+        assert_eq!(
+            GroupRule {
+                symbols: "#+".to_string(),
+                count: 3,
+                combine: Combine::Sum
+            },
+            GroupRule::parse("#+:3:sum").unwrap()
+        );
+        assert!(GroupRule::parse("*:2").is_err());
+        assert!(GroupRule::parse(":2:sum").is_err());
+        assert!(GroupRule::parse("*:two:sum").is_err());
+        assert!(GroupRule::parse("*:2:max").is_err());
+    }
+
+    #[test]
+    fn combine_overflow() {
+        let product = GroupRule::gears();
+        let sum = GroupRule::parse("*:2:sum").unwrap();
+
+        assert_eq!(Some(-6), product.combine([2, -3].into_iter()));
+        assert_eq!(Some(-1), sum.combine([2, -3].into_iter()));
+        assert_eq!(None, product.combine([i64::MAX, 2].into_iter()));
+        assert_eq!(None, sum.combine([i64::MAX, 1].into_iter()));
+    }
+
+    #[test]
+    fn symbol_classes() {
+        let rules = Rules {
+            empty: ".~".to_string(),
+            symbols: Some("*#".to_string()),
+            groups: Vec::new(),
+            ..Rules::default()
+        };
+
+        assert!(rules.is_symbol('*'));
+        assert!(!rules.is_symbol('~'));
+        assert!(!rules.is_symbol('$'));
+        assert!(Rules::default().is_symbol('$'));
+        assert!(!Rules::default().is_symbol('7'));
+    }
+}
@@ -0,0 +1,197 @@
+use std::fmt;
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Card {
+    pub id: u32,
+    pub winning: Vec<u32>,
+    pub have: Vec<u32>,
+}
+
+fn parse_numbers(input: &str) -> Result<Vec<u32>, String> {
+    return input
+        .split_whitespace()
+        .map(|it| {
+            it.parse()
+                .map_err(|err| format!("invalid number '{it}': {err}"))
+        })
+        .collect();
+}
+
+/// Parses a line like `Card 1: 41 48 | 83 86 6`.
+pub fn parse_card(input: &str) -> Result<Card, String> {
+    let line = input.trim();
+    let Some((label, numbers)) = line.split_once(":") else {
+        return Err(format!("missing ':' in '{line}'"));
+    };
+    let Some(id) = label.strip_prefix("Card") else {
+        return Err(format!("expected 'Card N:' in '{line}'"));
+    };
+    let id = id
+        .trim()
+        .parse()
+        .map_err(|err| format!("invalid card id in '{line}': {err}"))?;
+    let Some((winning, have)) = numbers.split_once("|") else {
+        return Err(format!("missing '|' in '{line}'"));
+    };
+    return Ok(Card {
+        id,
+        winning: parse_numbers(winning)?,
+        have: parse_numbers(have)?,
+    });
+}
+
+/// Something about the card ids that would make the cascade count the wrong cards.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum CardIssue {
+    /// Cards `from..=to` are missing.
+    Gap {
+        from: u32,
+        to: u32,
+    },
+    Duplicate {
+        id: u32,
+    },
+    /// Card `id` comes after card `previous` in the input.
+    OutOfOrder {
+        id: u32,
+        previous: u32,
+    },
+}
+
+impl fmt::Display for CardIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return match self {
+            CardIssue::Gap { from, to } if from == to => write!(f, "card {from} is missing"),
+            CardIssue::Gap { from, to } => write!(f, "cards {from} to {to} are missing"),
+            CardIssue::Duplicate { id } => write!(f, "card {id} appears more than once"),
+            CardIssue::OutOfOrder { id, previous } => {
+                write!(f, "card {id} comes after card {previous}")
+            }
+        };
+    }
+}
+
+/// What to do with cards that are not listed in order.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Order {
+    /// Treat them as an error.
+    Report,
+    /// Sort them by id. Gaps and duplicates are still errors.
+    Reorder,
+}
+
+/// Issues with the ids of the cards, which should be numbered from 1 without gaps.
+pub fn check(cards: &[Card]) -> Vec<CardIssue> {
+    let mut result = Vec::new();
+    for pair in cards.windows(2) {
+        if pair[1].id < pair[0].id {
+            result.push(CardIssue::OutOfOrder {
+                id: pair[1].id,
+                previous: pair[0].id,
+            });
+        }
+    }
+    let mut ids: Vec<u32> = cards.iter().map(|it| it.id).collect();
+    ids.sort();
+    let mut expected = 1;
+    for (index, id) in ids.iter().enumerate() {
+        if index > 0 && ids[index - 1] == *id {
+            if ids.get(index + 1) != Some(id) {
+                result.push(CardIssue::Duplicate { id: *id });
+            }
+            continue;
+        }
+        if *id > expected {
+            result.push(CardIssue::Gap {
+                from: expected,
+                to: id - 1,
+            });
+        }
+        expected = id + 1;
+    }
+    return result;
+}
+
+/// Parses the cards and puts them in order, or explains why that is not possible.
+pub fn parse_cards(input: &str, order: Order) -> Result<Vec<Card>, String> {
+    let mut cards = Vec::new();
+    for (index, line) in input.lines().enumerate() {
+        cards.push(parse_card(line).map_err(|err| format!("line {}: {err}", index + 1))?);
+    }
+    let issues: Vec<String> = check(&cards)
+        .iter()
+        .filter(|it| order == Order::Report || !matches!(it, CardIssue::OutOfOrder { .. }))
+        .map(|it| it.to_string())
+        .collect();
+    if !issues.is_empty() {
+        return Err(issues.join("\n"));
+    }
+    cards.sort_by_key(|it| it.id);
+    return Ok(cards);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parsing() {
+        assert_eq!(
+            Card {
+                id: 12,
+                winning: vec![41, 48],
+                have: vec![83, 6],
+            },
+            parse_card("  Card  12: 41 48 | 83  6").unwrap()
+        );
+        assert!(parse_card("Card 1 41 | 2").is_err());
+        assert!(parse_card("Game 1: 41 | 2").is_err());
+        assert!(parse_card("Card x: 41 | 2").is_err());
+        assert!(parse_card("Card 1: 41 2").is_err());
+        assert!(parse_card("Card 1: 41 | -2").is_err());
+    }
+
+    #[test]
+    fn ids() {
+        let input = "Card 2: 1 | 1
+                     Card 1: 1 | 2
+                     Card 5: 3 | 4
+                     Card 5: 3 | 4
+                     Card 5: 3 | 4
+                     Card 7: 3 | 4";
+        let cards: Vec<Card> = input.lines().map(|it| parse_card(it).unwrap()).collect();
+
+        assert_eq!(
+            vec![
+                CardIssue::OutOfOrder { id: 1, previous: 2 },
+                CardIssue::Gap { from: 3, to: 4 },
+                CardIssue::Duplicate { id: 5 },
+                CardIssue::Gap { from: 6, to: 6 },
+            ],
+            check(&cards)
+        );
+        assert_eq!(
+            "cards 3 to 4 are missing\ncard 5 appears more than once\ncard 6 is missing",
+            parse_cards(input, Order::Reorder).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn reorder() {
+        let input = "Card 2: 1 | 1
+                     Card 3: 1 | 2
+                     Card 1: 3 | 4";
+
+        let cards = parse_cards(input, Order::Reorder).unwrap();
+
+        assert_eq!(
+            vec![1, 2, 3],
+            cards.iter().map(|it| it.id).collect::<Vec<u32>>()
+        );
+        assert_eq!(
+            "card 1 comes after card 3",
+            parse_cards(input, Order::Report).unwrap_err()
+        );
+        assert!(parse_cards("Card 2: 1 | 1", Order::Reorder).is_err());
+    }
+}
@@ -0,0 +1,302 @@
+use std::ops::Range;
+
+/// Which cards a winning card earns copies of.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum PrizeRule {
+    /// One copy of each of the next `wins` cards.
+    NextCards,
+    /// The n-th win earns one copy of the card `offsets[n - 1]` places further down.
+    /// Wins beyond the list earn nothing.
+    Offsets(Vec<usize>),
+    /// Like `NextCards`, but the n-th card below earns `weights[n - 1]` copies, or one
+    /// copy when the list is shorter than the wins.
+    Weighted(Vec<u64>),
+}
+
+impl PrizeRule {
+    /// Parses `next`, `offsets:A,B,..` or `weighted:A,B,..`.
+    pub fn parse(spec: &str) -> Result<PrizeRule, String> {
+        let (name, values) = spec.split_once(":").unwrap_or((spec, ""));
+        return match name {
+            "next" if values.is_empty() => Ok(PrizeRule::NextCards),
+            "offsets" => {
+                let offsets: Vec<usize> = parse_list(values, spec)?;
+                if offsets.contains(&0) {
+                    return Err(format!("a card cannot win copies of itself in '{spec}'"));
+                }
+                Ok(PrizeRule::Offsets(offsets))
+            }
+            "weighted" => Ok(PrizeRule::Weighted(parse_list(values, spec)?)),
+            _ => Err(format!(
+                "expected next, offsets:LIST or weighted:LIST, got '{spec}'"
+            )),
+        };
+    }
+
+    /// Copies a single copy of a card with `wins` matches earns, as runs of offsets that
+    /// each earn the same number of copies.
+    pub fn prizes(&self, wins: u32) -> Vec<(Range<usize>, u64)> {
+        let wins = wins as usize;
+        return match self {
+            PrizeRule::NextCards if wins == 0 => Vec::new(),
+            PrizeRule::NextCards => vec![(1..wins + 1, 1)],
+            PrizeRule::Offsets(offsets) => offsets
+                .iter()
+                .take(wins)
+                .map(|it| (*it..it.saturating_add(1), 1))
+                .collect(),
+            PrizeRule::Weighted(weights) => {
+                let mut result: Vec<(Range<usize>, u64)> = weights
+                    .iter()
+                    .take(wins)
+                    .enumerate()
+                    .map(|(index, weight)| (index + 1..index + 2, *weight))
+                    .collect();
+                if wins > weights.len() {
+                    result.push((weights.len() + 1..wins + 1, 1));
+                }
+                result
+            }
+        };
+    }
+}
+
+/// What to do with prizes for cards past the last one.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Overflow {
+    /// Drop them.
+    Clamp,
+    Error,
+}
+
+/// How many points a card is worth.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Points {
+    /// One point for the first win, doubled for every further one.
+    Doubling,
+    PerWin(u64),
+    /// Points indexed by the number of wins.
+    Table(Vec<u64>),
+}
+
+impl Points {
+    /// Parses `doubling`, `per-win:N` or `table:A,B,..`.
+    pub fn parse(spec: &str) -> Result<Points, String> {
+        let (name, values) = spec.split_once(":").unwrap_or((spec, ""));
+        return match name {
+            "doubling" if values.is_empty() => Ok(Points::Doubling),
+            "per-win" => values
+                .parse()
+                .map(Points::PerWin)
+                .map_err(|err| format!("invalid points in '{spec}': {err}")),
+            "table" => Ok(Points::Table(parse_list(values, spec)?)),
+            _ => Err(format!(
+                "expected doubling, per-win:N or table:LIST, got '{spec}'"
+            )),
+        };
+    }
+
+    /// Points for a card with `wins` matches, an error if they do not fit in a u64.
+    pub fn score(&self, wins: u32) -> Result<u64, String> {
+        return match self {
+            Points::Doubling if wins == 0 => Ok(0),
+            Points::Doubling => 1u64
+                .checked_shl(wins - 1)
+                .ok_or_else(|| format!("{wins} wins are worth too many points")),
+            Points::PerWin(points) => points
+                .checked_mul(wins as u64)
+                .ok_or_else(|| format!("{wins} wins are worth too many points")),
+            Points::Table(table) => table
+                .get(wins as usize)
+                .copied()
+                .ok_or_else(|| format!("no points listed for {wins} wins")),
+        };
+    }
+}
+
+fn parse_list<T: std::str::FromStr>(values: &str, spec: &str) -> Result<Vec<T>, String>
+where
+    T::Err: std::fmt::Display,
+{
+    if values.is_empty() {
+        return Err(format!("empty list in '{spec}'"));
+    }
+    return values
+        .split(",")
+        .map(|it| {
+            it.trim()
+                .parse()
+                .map_err(|err| format!("invalid value '{it}' in '{spec}': {err}"))
+        })
+        .collect();
+}
+
+/// The number of copies held of every card, starting with one original of each.
+///
+/// `overflow` decides what happens to prizes for cards past the last one: `Clamp` drops
+/// them and keeps the prizes that still fit, `Error` fails on the first card winning any.
+/// Every run of prizes is added to a difference array in constant time, so this stays
+/// linear in the number of cards no matter how many copies they win.
+pub fn copies(wins: &[u32], rule: &PrizeRule, overflow: Overflow) -> Result<Vec<u64>, String> {
+    let count = wins.len();
+    // Copies won from the given card on, and the card after the last one they cover.
+    let mut starts = vec![0u64; count + 1];
+    let mut ends = vec![0u64; count + 1];
+    let mut won = 0u64;
+    let mut copies = Vec::with_capacity(count);
+    for (index, wins) in wins.iter().enumerate() {
+        won = won
+            .checked_add(starts[index])
+            .ok_or_else(|| format!("copies of card {} overflow", index + 1))?;
+        won -= ends[index];
+        let held = won
+            .checked_add(1)
+            .ok_or_else(|| format!("copies of card {} overflow", index + 1))?;
+        copies.push(held);
+
+        for (offsets, each) in rule.prizes(*wins) {
+            let start = index.saturating_add(offsets.start).min(count);
+            let end = index.saturating_add(offsets.end).min(count);
+            if end - start < offsets.len() && overflow == Overflow::Error {
+                return Err(format!("card {} wins copies past the last card", index + 1));
+            }
+            if start == end {
+                continue;
+            }
+            let amount = held
+                .checked_mul(each)
+                .ok_or_else(|| format!("copies won by card {} overflow", index + 1))?;
+            starts[start] = starts[start]
+                .checked_add(amount)
+                .ok_or_else(|| format!("copies of card {} overflow", start + 1))?;
+            // These copies are all still held by the next card, so count it as that card
+            // overflowing.
+            ends[end] = ends[end]
+                .checked_add(amount)
+                .ok_or_else(|| format!("copies of card {} overflow", index + 2))?;
+        }
+    }
+    return Ok(copies);
+}
+
+pub fn total_points(wins: &[u32], points: &Points) -> Result<u64, String> {
+    let mut sum = 0u64;
+    for wins in wins {
+        sum = sum
+            .checked_add(points.score(*wins)?)
+            .ok_or("total points overflow")?;
+    }
+    return Ok(sum);
+}
+
+pub fn total_copies(wins: &[u32], rule: &PrizeRule, overflow: Overflow) -> Result<u64, String> {
+    let mut sum = 0u64;
+    for copies in copies(wins, rule, overflow)? {
+        sum = sum.checked_add(copies).ok_or("total copies overflow")?;
+    }
+    return Ok(sum);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WINS: [u32; 6] = [4, 2, 2, 1, 0, 0];
+
+    #[test]
+    fn prize_rules() {
+        assert_eq!(
+            vec![1, 2, 4, 8, 14, 1],
+            copies(&WINS, &PrizeRule::NextCards, Overflow::Clamp).unwrap()
+        );
+        // Card 1 earns copies of cards 3 and 6, card 2 of card 4, card 3 of card 5 and
+        // card 4 of card 6.
+        let offsets = PrizeRule::parse("offsets:2,5").unwrap();
+        assert_eq!(
+            vec![1, 1, 2, 2, 3, 4],
+            copies(&WINS, &offsets, Overflow::Clamp).unwrap()
+        );
+        let weighted = PrizeRule::parse("weighted:2").unwrap();
+        assert_eq!(
+            vec![1, 3, 8, 21, 52, 1],
+            copies(&WINS, &weighted, Overflow::Clamp).unwrap()
+        );
+        assert_eq!(86, total_copies(&WINS, &weighted, Overflow::Clamp).unwrap());
+        assert!(copies(&[1; 70], &PrizeRule::Weighted(vec![2]), Overflow::Clamp).is_err());
+    }
+
+    #[test]
+    fn past_the_last_card() {
+        assert!(copies(&WINS, &PrizeRule::NextCards, Overflow::Error).is_ok());
+        assert!(copies(&[0, 0, 1], &PrizeRule::NextCards, Overflow::Error).is_err());
+        assert_eq!(
+            Err("card 2 wins copies past the last card".to_string()),
+            copies(
+                &WINS,
+                &PrizeRule::parse("offsets:2,5").unwrap(),
+                Overflow::Error
+            )
+        );
+    }
+
+    #[test]
+    fn adversarial_cascade() {
+        // Every card wins all cards below it, so card n is held 2^(n-1) times.
+        let wins = [u32::MAX; 64];
+        let copies = copies(&wins, &PrizeRule::NextCards, Overflow::Clamp).unwrap();
+        assert_eq!(1 << 63, copies[63]);
+        assert_eq!(
+            u64::MAX,
+            total_copies(&wins, &PrizeRule::NextCards, Overflow::Clamp).unwrap()
+        );
+        assert!(super::copies(&[u32::MAX; 65], &PrizeRule::NextCards, Overflow::Clamp).is_err());
+
+        let mut wins = vec![0; 1_000_000];
+        wins[..20].fill(1_000_000);
+        let copies = super::copies(&wins, &PrizeRule::NextCards, Overflow::Clamp).unwrap();
+        assert_eq!(1 << 19, copies[19]);
+        assert_eq!(1 << 20, copies[999_999]);
+    }
+
+    #[test]
+    fn copies_ending_together_overflow() {
+        // All copies won from the second card on run to the end, and the copies ending
+        // there add up past u64::MAX one card before any single card holds that many.
+        let mut wins = vec![1];
+        wins.extend([u32::MAX; 65]);
+
+        assert_eq!(
+            Err("copies of card 66 overflow".to_string()),
+            copies(&wins, &PrizeRule::NextCards, Overflow::Clamp)
+        );
+        assert!(copies(&wins[..65], &PrizeRule::NextCards, Overflow::Clamp).is_ok());
+    }
+
+    #[test]
+    fn point_formulas() {
+        assert_eq!(13, total_points(&WINS, &Points::Doubling).unwrap());
+        assert_eq!(
+            27,
+            total_points(&WINS, &Points::parse("per-win:3").unwrap()).unwrap()
+        );
+        let table = Points::parse("table:0,1,5,9,20").unwrap();
+        assert_eq!(31, total_points(&WINS, &table).unwrap());
+        assert!(Points::Table(vec![0, 1]).score(2).is_err());
+        assert_eq!(1 << 63, Points::Doubling.score(64).unwrap());
+        assert!(Points::Doubling.score(65).is_err());
+    }
+
+    #[test]
+    fn specs() {
+        assert_eq!(PrizeRule::NextCards, PrizeRule::parse("next").unwrap());
+        assert_eq!(
+            PrizeRule::Offsets(vec![1, 3]),
+            PrizeRule::parse("offsets:1, 3").unwrap()
+        );
+        assert!(PrizeRule::parse("offsets:0").is_err());
+        assert!(PrizeRule::parse("weighted:").is_err());
+        assert!(PrizeRule::parse("next:1").is_err());
+        assert_eq!(Points::PerWin(2), Points::parse("per-win:2").unwrap());
+        assert!(Points::parse("squared").is_err());
+    }
+}
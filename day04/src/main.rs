@@ -1,86 +1,214 @@
-fn parse_numbers(input: &str) -> Vec<u32> {
-    return input
-        .split_whitespace()
-        .map(|it| it.parse().unwrap())
-        .collect();
+#![allow(clippy::needless_return)]
+
+mod cards;
+mod cascade;
+mod report;
+
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+use cards::{Card, Order};
+use cascade::{Overflow, Points, PrizeRule};
+
+/// How to treat a number that occurs more than once on the same side of a card.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Duplicates {
+    /// Every copy of a number we have counts as its own win.
+    Count,
+    /// Each distinct number wins at most once.
+    Dedupe,
+    /// Duplicates on either side are an error.
+    Reject,
 }
 
-fn parse_line(input: &str) -> (Vec<u32>, Vec<u32>) {
-    let parts: Vec<&str> = input.trim().split(":").collect();
-    let numbers: Vec<&str> = parts[1].split("|").collect();
-    let winning_numbers: Vec<u32> = parse_numbers(numbers[0].trim());
-    let have_numbers: Vec<u32> = parse_numbers(numbers[1].trim());
-    return (winning_numbers, have_numbers);
+/// Everything that decides how the cards are read and scored.
+#[derive(Clone, PartialEq, Eq, Debug)]
+struct Rules {
+    duplicates: Duplicates,
+    order: Order,
+    points: Points,
+    prizes: PrizeRule,
+    overflow: Overflow,
 }
 
-fn count_wins(winning_numbers: &Vec<u32>, have_numbers: &Vec<u32>) -> u32 {
-    let mut wins = 0;
-    for number in have_numbers {
-        for winner in winning_numbers {
-            if *number == *winner {
-                wins += 1;
-                break;
-            }
-        }
+impl Default for Rules {
+    fn default() -> Rules {
+        return Rules {
+            duplicates: Duplicates::Count,
+            order: Order::Report,
+            points: Points::Doubling,
+            prizes: PrizeRule::NextCards,
+            overflow: Overflow::Clamp,
+        };
     }
-    return wins;
 }
 
-fn solve(input: &str) -> u32 {
-    let mut sum = 0;
-    for line in input.lines() {
-        let (winning_numbers, have_numbers) = parse_line(line);
-
-        let wins = count_wins(&winning_numbers, &have_numbers);
+fn find_duplicate(numbers: &[u32]) -> Option<u32> {
+    let mut seen = HashSet::new();
+    return numbers.iter().copied().find(|it| !seen.insert(*it));
+}
 
-        if wins > 0 {
-            sum += (2u32).pow(wins - 1);
+fn count_wins(
+    winning_numbers: &[u32],
+    have_numbers: &[u32],
+    duplicates: Duplicates,
+) -> Result<u32, String> {
+    if duplicates == Duplicates::Reject {
+        if let Some(number) = find_duplicate(winning_numbers) {
+            return Err(format!("winning number {number} listed more than once"));
+        }
+        if let Some(number) = find_duplicate(have_numbers) {
+            return Err(format!("number {number} listed more than once"));
         }
     }
-    return sum;
+    let winners: HashSet<u32> = winning_numbers.iter().copied().collect();
+    let wins = if duplicates == Duplicates::Dedupe {
+        let have: HashSet<u32> = have_numbers.iter().copied().collect();
+        have.intersection(&winners).count()
+    } else {
+        have_numbers
+            .iter()
+            .filter(|it| winners.contains(it))
+            .count()
+    };
+    return Ok(u32::try_from(wins).unwrap());
 }
 
-fn solve2(input: &str) -> u32 {
-    let mut sum = 0;
-    let mut open_intervals: Vec<u32> = Vec::new(); // current open intervals defined by remaining items in interval
-    for line in input.lines() {
-        let (winning_numbers, have_numbers) = parse_line(line);
+/// Wins of every card, in the order given.
+fn card_wins(cards: &[Card], rules: &Rules) -> Result<Vec<u32>, String> {
+    let mut result = Vec::new();
+    for card in cards {
+        result.push(count_wins(&card.winning, &card.have, rules.duplicates)?);
+    }
+    return Ok(result);
+}
 
-        sum += 1; // this card
+fn solve(cards: &[Card], rules: &Rules) -> Result<u64, String> {
+    let wins = card_wins(cards, rules)?;
+    return cascade::total_points(&wins, &rules.points);
+}
 
-        let extras = u32::try_from(open_intervals.len()).unwrap();
-        open_intervals.retain_mut(|it| {
-            *it -= 1;
-            *it > 0
-        });
-        sum += extras;
+fn solve2(cards: &[Card], rules: &Rules) -> Result<u64, String> {
+    let wins = card_wins(cards, rules)?;
+    return cascade::total_copies(&wins, &rules.prizes, rules.overflow);
+}
 
-        let wins = count_wins(&winning_numbers, &have_numbers);
+fn print_report(reports: &[report::CardReport]) {
+    println!(
+        "{:>6} {:>6} {:>20} {:>20}  from",
+        "card", "wins", "points", "copies"
+    );
+    for report in reports {
+        let sources: Vec<String> = report
+            .sources
+            .iter()
+            .map(|(card, copies)| format!("{card} ({copies})"))
+            .collect();
+        println!(
+            "{:>6} {:>6} {:>20} {:>20}  {}",
+            report.card,
+            report.wins,
+            report.points,
+            report.copies,
+            sources.join(", ")
+        );
+    }
+}
 
-        if wins > 0 {
-            open_intervals.push(wins);
-            for _ in 0..extras {
-                // copies win as well
-                open_intervals.push(wins);
+struct Args {
+    input: PathBuf,
+    rules: Rules,
+    report: bool,
+    dot: Option<PathBuf>,
+}
+
+const USAGE: &str = "usage: day04 [INPUT] [--duplicates count|dedupe|reject]
+             [--points doubling|per-win:N|table:LIST]
+             [--prizes next|offsets:LIST|weighted:LIST] [--overflow clamp|error]
+             [--reorder] [--report] [--dot FILE]";
+
+fn parse_args(args: impl Iterator<Item = String>) -> Result<Args, String> {
+    let mut input = None;
+    let mut rules = Rules::default();
+    let mut report = false;
+    let mut dot = None;
+    let mut args = args;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--duplicates" => {
+                rules.duplicates = match args.next().as_deref() {
+                    Some("count") => Duplicates::Count,
+                    Some("dedupe") => Duplicates::Dedupe,
+                    Some("reject") => Duplicates::Reject,
+                    _ => return Err("--duplicates needs count, dedupe or reject".to_string()),
+                }
+            }
+            "--points" => {
+                rules.points = Points::parse(&args.next().ok_or("--points needs a formula")?)?;
             }
+            "--prizes" => {
+                rules.prizes = PrizeRule::parse(&args.next().ok_or("--prizes needs a rule")?)?;
+            }
+            "--overflow" => {
+                rules.overflow = match args.next().as_deref() {
+                    Some("clamp") => Overflow::Clamp,
+                    Some("error") => Overflow::Error,
+                    _ => return Err("--overflow needs clamp or error".to_string()),
+                }
+            }
+            "--reorder" => rules.order = Order::Reorder,
+            "--report" => report = true,
+            "--dot" => dot = Some(PathBuf::from(args.next().ok_or("--dot needs a file")?)),
+            _ if arg.starts_with("--") => return Err(format!("unknown option '{arg}'")),
+            _ if input.is_none() => input = Some(PathBuf::from(arg)),
+            _ => return Err(format!("unexpected argument '{arg}'")),
         }
     }
-    return sum;
+    return Ok(Args {
+        input: input.unwrap_or_else(|| PathBuf::from("input.txt")),
+        rules,
+        report,
+        dot,
+    });
+}
+
+fn fail<T>(err: String) -> T {
+    eprintln!("{err}");
+    std::process::exit(1);
 }
 
 fn main() {
-    let input = std::fs::read_to_string("input.txt").unwrap();
-    let output = solve(input.as_str());
+    let args = parse_args(std::env::args().skip(1)).unwrap_or_else(|err| {
+        eprintln!("{err}\n{USAGE}");
+        std::process::exit(2);
+    });
+    let input = std::fs::read_to_string(&args.input).unwrap();
+    let cards = cards::parse_cards(input.as_str(), args.rules.order).unwrap_or_else(fail);
+    let output = solve(&cards, &args.rules).unwrap_or_else(fail);
     println!("{output}");
 
-    let output = solve2(input.as_str());
+    let output = solve2(&cards, &args.rules).unwrap_or_else(fail);
     println!("{output}");
+
+    if args.report || args.dot.is_some() {
+        let reports = report::report(&cards, &args.rules).unwrap_or_else(fail);
+        if args.report {
+            print_report(&reports);
+        }
+        if let Some(path) = args.dot {
+            std::fs::write(path, report::dot(&reports)).unwrap();
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn parse(input: &str, rules: &Rules) -> Vec<Card> {
+        return cards::parse_cards(input, rules.order).unwrap();
+    }
+
     #[test]
     fn sample() {
         let input = "Card 1: 41 48 83 86 17 | 83 86  6 31 17  9 48 53
@@ -91,7 +219,7 @@ mod tests {
                      Card 6: 31 18 13 56 72 | 74 77 10 23 35 67 36 11";
         let expected_output = 13;
 
-        let output = solve(input);
+        let output = solve(&parse(input, &Rules::default()), &Rules::default()).unwrap();
 
         assert_eq!(expected_output, output);
     }
@@ -106,8 +234,44 @@ mod tests {
                      Card 6: 31 18 13 56 72 | 74 77 10 23 35 67 36 11";
         let expected_output = 30;
 
-        let output = solve2(input);
+        let output = solve2(&parse(input, &Rules::default()), &Rules::default()).unwrap();
 
         assert_eq!(expected_output, output);
     }
+
+    #[test]
+    fn duplicates() {
+        let card = cards::parse_card("Card 1: 5 7 5 | 5 5 9 7 1234").unwrap();
+        let count = |duplicates| count_wins(&card.winning, &card.have, duplicates);
+
+        assert_eq!(Ok(3), count(Duplicates::Count));
+        assert_eq!(Ok(2), count(Duplicates::Dedupe));
+        assert!(count(Duplicates::Reject).is_err());
+        assert!(count_wins(&[1, 2], &[2, 2], Duplicates::Reject).is_err());
+        assert_eq!(
+            Ok(1),
+            count_wins(&[1000000], &[1000000], Duplicates::Reject)
+        );
+    }
+
+    #[test]
+    fn card_order() {
+        let input = "Card 3: 1 | 1
+                     Card 1: 1 2 3 | 1 2 3
+                     Card 2: 1 | 5";
+        let reorder = Rules {
+            order: Order::Reorder,
+            ..Rules::default()
+        };
+
+        assert!(cards::parse_cards(input, Order::Report).is_err());
+        let cards = parse(input, &reorder);
+        // Cards 1 and 3 both win copies of cards past the last one.
+        assert_eq!(Ok(5), solve2(&cards, &reorder));
+        let strict = Rules {
+            overflow: Overflow::Error,
+            ..reorder
+        };
+        assert!(solve2(&cards, &strict).is_err());
+    }
 }
@@ -0,0 +1,118 @@
+use std::fmt::Write;
+
+use crate::cards::Card;
+use crate::cascade;
+use crate::Rules;
+
+/// How a single card did.
+#[derive(Debug, PartialEq, Eq)]
+pub struct CardReport {
+    /// The card's id.
+    pub card: u32,
+    pub wins: u32,
+    pub points: u64,
+    /// Copies held, including the original.
+    pub copies: u64,
+    /// Earlier cards that won copies of this one, with how many each contributed.
+    pub sources: Vec<(u32, u64)>,
+}
+
+/// Reports for the cards, which must be in order as returned by `parse_cards`.
+pub fn report(cards: &[Card], rules: &Rules) -> Result<Vec<CardReport>, String> {
+    let wins = crate::card_wins(cards, rules)?;
+    let copies = cascade::copies(&wins, &rules.prizes, rules.overflow)?;
+    let mut result = Vec::with_capacity(wins.len());
+    for (index, wins) in wins.iter().enumerate() {
+        result.push(CardReport {
+            card: cards[index].id,
+            wins: *wins,
+            points: rules.points.score(*wins)?,
+            copies: copies[index],
+            sources: Vec::new(),
+        });
+    }
+    // Unlike the cascade itself this visits every single prize, which is fine for cards
+    // someone is going to read through.
+    for index in 0..result.len() {
+        let card = result[index].card;
+        let held = result[index].copies;
+        for (offsets, each) in rules.prizes.prizes(result[index].wins) {
+            let start = index.saturating_add(offsets.start).min(result.len());
+            let end = index.saturating_add(offsets.end).min(result.len());
+            for target in result[start..end].iter_mut() {
+                // Fits, since it is part of the target's copies.
+                target.sources.push((card, held * each));
+            }
+        }
+    }
+    return Ok(result);
+}
+
+/// The copy graph in GraphViz format, with an edge from every card to each card it won
+/// copies of.
+pub fn dot(reports: &[CardReport]) -> String {
+    let mut out = String::new();
+    writeln!(out, "digraph cards {{").unwrap();
+    writeln!(out, "    node [shape=box];").unwrap();
+    for report in reports {
+        writeln!(
+            out,
+            r#"    {} [label="Card {}\n{} wins, {} points\n{} copies"];"#,
+            report.card, report.card, report.wins, report.points, report.copies
+        )
+        .unwrap();
+    }
+    for report in reports {
+        for (source, copies) in report.sources.iter() {
+            writeln!(
+                out,
+                r#"    {source} -> {} [label="{copies}"];"#,
+                report.card
+            )
+            .unwrap();
+        }
+    }
+    writeln!(out, "}}").unwrap();
+    return out;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cards::Order;
+
+    #[test]
+    fn sample() {
+        let input = "Card 1: 41 48 83 86 17 | 83 86  6 31 17  9 48 53
+                     Card 2: 13 32 20 16 61 | 61 30 68 82 17 32 24 19
+                     Card 3:  1 21 53 59 44 | 69 82 63 72 16 21 14  1
+                     Card 4: 41 92 73 84 69 | 59 84 76 51 58  5 54 83
+                     Card 5: 87 83 26 28 32 | 88 30 70 12 93 22 82 36
+                     Card 6: 31 18 13 56 72 | 74 77 10 23 35 67 36 11";
+        let cards = crate::cards::parse_cards(input, Order::Report).unwrap();
+
+        let reports = report(&cards, &Rules::default()).unwrap();
+
+        assert_eq!(
+            CardReport {
+                card: 4,
+                wins: 1,
+                points: 1,
+                copies: 8,
+                sources: vec![(1, 1), (2, 2), (3, 4)],
+            },
+            reports[3]
+        );
+        assert_eq!(vec![(1, 1), (3, 4), (4, 8)], reports[4].sources);
+        for report in reports.iter() {
+            let won: u64 = report.sources.iter().map(|it| it.1).sum();
+            assert_eq!(report.copies, won + 1);
+        }
+
+        let dot = dot(&reports);
+        assert!(dot.starts_with("digraph cards {"));
+        assert!(dot.contains(r#"5 [label="Card 5\n0 wins, 0 points\n14 copies"];"#));
+        assert!(dot.contains(r#"3 -> 5 [label="4"];"#));
+        assert_eq!(9, dot.matches(" -> ").count());
+    }
+}
@@ -0,0 +1,281 @@
+use crate::range_map::{Entry, RangeMap, Segment};
+use crate::validate::validate;
+
+/// One `X-to-Y map:` block.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Conversion {
+    pub from: String,
+    pub to: String,
+    pub map: RangeMap,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Almanac {
+    pub seeds: Vec<u64>,
+    /// In the order they are listed. Together they form a graph of categories without
+    /// cycles, usually a single path from seed to location.
+    pub conversions: Vec<Conversion>,
+}
+
+/// One `X-to-Y map:` block as written, with the 1-based line of the header and of every
+/// entry.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Block {
+    pub line: usize,
+    pub from: String,
+    pub to: String,
+    pub entries: Vec<(usize, Entry)>,
+}
+
+/// An almanac as written, before the maps are checked. Lines that cannot be read at all
+/// end up in `errors`, with their line number.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct Listing {
+    pub seeds: Vec<u64>,
+    pub blocks: Vec<Block>,
+    pub errors: Vec<(usize, String)>,
+}
+
+fn parse_seeds(line: &str) -> Result<Vec<u64>, String> {
+    let Some(seeds) = line.strip_prefix("seeds: ") else {
+        return Err(format!("expected 'seeds: ...', got '{line}'"));
+    };
+    return seeds
+        .split_whitespace()
+        .map(|it| {
+            it.parse()
+                .map_err(|err| format!("invalid seed '{it}': {err}"))
+        })
+        .collect();
+}
+
+fn parse_header(line: &str) -> Result<(String, String), String> {
+    let Some(name) = line.strip_suffix(" map:") else {
+        return Err(format!("expected 'X-to-Y map:', got '{line}'"));
+    };
+    let Some((from, to)) = name.split_once("-to-") else {
+        return Err(format!("expected 'X-to-Y map:', got '{line}'"));
+    };
+    if from.is_empty() || to.is_empty() {
+        return Err(format!("missing category in '{line}'"));
+    }
+    return Ok((from.to_string(), to.to_string()));
+}
+
+impl Listing {
+    pub fn read(input: &str) -> Listing {
+        let mut result = Listing::default();
+        let mut lines = input.lines().map(|it| it.trim()).enumerate();
+        let seeds = lines.next().map(|(_, it)| it).unwrap_or_default();
+        match parse_seeds(seeds) {
+            Ok(seeds) => result.seeds = seeds,
+            Err(err) => result.errors.push((1, err)),
+        }
+        // Entries below a broken header belong to no known map and are skipped.
+        let mut skipping = false;
+        for (index, text) in lines {
+            let line = index + 1;
+            if text.is_empty() {
+                continue;
+            }
+            if text.contains(":") {
+                match parse_header(text) {
+                    Ok((from, to)) => result.blocks.push(Block {
+                        line,
+                        from,
+                        to,
+                        entries: Vec::new(),
+                    }),
+                    Err(err) => result.errors.push((line, err)),
+                }
+                skipping = result.blocks.last().is_none_or(|it| it.line != line);
+                continue;
+            }
+            if skipping {
+                continue;
+            }
+            let Some(block) = result.blocks.last_mut() else {
+                result
+                    .errors
+                    .push((line, format!("'{text}' is not part of a map")));
+                continue;
+            };
+            match Entry::parse(text) {
+                Ok(entry) => block.entries.push((line, entry)),
+                Err(err) => result.errors.push((line, err)),
+            }
+        }
+        return result;
+    }
+}
+
+impl Almanac {
+    /// Fails on the first problem that leaves the maps ambiguous, see [`validate`].
+    pub fn parse(input: &str) -> Result<Almanac, String> {
+        let listing = Listing::read(input);
+        if let Some(issue) = validate(&listing).iter().find(|it| it.problem.is_fatal()) {
+            return Err(issue.to_string());
+        }
+        let mut conversions = Vec::new();
+        for block in listing.blocks {
+            let segments = block
+                .entries
+                .iter()
+                .map(|(_, entry)| entry.segment())
+                .collect::<Result<Vec<Segment>, String>>()?;
+            let (from, to) = (block.from, block.to);
+            let map = RangeMap::new(segments).map_err(|err| format!("{from}-to-{to}: {err}"))?;
+            conversions.push(Conversion { from, to, map });
+        }
+        let almanac = Almanac {
+            seeds: listing.seeds,
+            conversions,
+        };
+        almanac.check_cycles()?;
+        return Ok(almanac);
+    }
+
+    /// Every category, in the order they first appear.
+    pub fn categories(&self) -> Vec<&str> {
+        let mut result: Vec<&str> = Vec::new();
+        for conversion in self.conversions.iter() {
+            for category in [conversion.from.as_str(), conversion.to.as_str()] {
+                if !result.contains(&category) {
+                    result.push(category);
+                }
+            }
+        }
+        return result;
+    }
+
+    fn check_cycles(&self) -> Result<(), String> {
+        // Repeatedly drop categories that nothing converts into; whatever is left is on
+        // a cycle.
+        let mut remaining: Vec<&Conversion> = self.conversions.iter().collect();
+        loop {
+            let count = remaining.len();
+            let targets: Vec<&str> = remaining.iter().map(|it| it.to.as_str()).collect();
+            remaining.retain(|it| targets.contains(&it.from.as_str()));
+            if remaining.is_empty() {
+                return Ok(());
+            }
+            if remaining.len() == count {
+                let cycle: Vec<String> = remaining
+                    .iter()
+                    .map(|it| format!("{}-to-{}", it.from, it.to))
+                    .collect();
+                return Err(format!("maps form a cycle: {}", cycle.join(", ")));
+            }
+        }
+    }
+
+    /// Indices of the conversions leading from one category to another, an error if there
+    /// is no way or more than one.
+    fn path(&self, from: &str, to: &str) -> Result<Vec<usize>, String> {
+        let mut paths: Vec<Vec<usize>> = Vec::new();
+        let mut stack: Vec<(&str, Vec<usize>)> = vec![(from, Vec::new())];
+        while let Some((category, path)) = stack.pop() {
+            if category == to {
+                paths.push(path);
+                if paths.len() > 1 {
+                    return Err(format!("more than one way leads from {from} to {to}"));
+                }
+                continue;
+            }
+            for (index, conversion) in self.conversions.iter().enumerate() {
+                if conversion.from == category {
+                    let mut next = path.clone();
+                    next.push(index);
+                    stack.push((&conversion.to, next));
+                }
+            }
+        }
+        return paths
+            .pop()
+            .ok_or_else(|| format!("no maps lead from {from} to {to}"));
+    }
+
+    /// The map from one category to another. Going against the direction of the maps,
+    /// like location to seed, works when the maps are one-to-one.
+    pub fn map_between(&self, from: &str, to: &str) -> Result<RangeMap, String> {
+        for category in [from, to] {
+            if !self.categories().contains(&category) {
+                return Err(format!("unknown category '{category}'"));
+            }
+        }
+        if let Ok(path) = self.path(from, to) {
+            return Ok(path.iter().fold(RangeMap::default(), |chain, index| {
+                chain.compose(&self.conversions[*index].map)
+            }));
+        }
+        let forward = self
+            .path(to, from)
+            .map_err(|_| self.path(from, to).unwrap_err())?;
+        let chain = forward.iter().fold(RangeMap::default(), |chain, index| {
+            chain.compose(&self.conversions[*index].map)
+        });
+        return chain
+            .inverse()
+            .map_err(|err| format!("cannot convert {from} back to {to}: {err}"));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INPUT: &str = "seeds: 1 2
+
+                         a-to-b map:
+                         10 0 5
+
+                         b-to-c map:
+                         0 10 10
+                         10 0 10
+
+                         a-to-d map:
+                         100 0 1";
+
+    #[test]
+    fn graph() {
+        let almanac = Almanac::parse(INPUT).unwrap();
+
+        assert_eq!(vec!["a", "b", "c", "d"], almanac.categories());
+        let a_to_c = almanac.map_between("a", "c").unwrap();
+        assert_eq!(0, a_to_c.apply(0));
+        assert_eq!(15, a_to_c.apply(5));
+        // b-to-c swaps two blocks, so it can be run backwards.
+        let c_to_b = almanac.map_between("c", "b").unwrap();
+        assert_eq!(10, c_to_b.apply(0));
+        assert_eq!(0, c_to_b.apply(10));
+        assert!(almanac.map_between("c", "a").is_err());
+        assert!(almanac.map_between("b", "d").is_err());
+        assert!(almanac.map_between("a", "x").is_err());
+        assert_eq!(RangeMap::default(), almanac.map_between("b", "b").unwrap());
+    }
+
+    #[test]
+    fn invalid_graphs() {
+        let cycle = "seeds: 1
+                     a-to-b map:
+                     b-to-a map:";
+        let twice = "seeds: 1
+                     a-to-b map:
+                     a-to-b map:";
+        let diamond = "seeds: 1
+                       a-to-b map:
+                       b-to-d map:
+                       a-to-c map:
+                       c-to-d map:";
+
+        assert_eq!(
+            Err("maps form a cycle: a-to-b, b-to-a".to_string()),
+            Almanac::parse(cycle)
+        );
+        assert!(Almanac::parse(twice).is_err());
+        assert!(Almanac::parse("seeds: 1\na to b map:").is_err());
+        let diamond = Almanac::parse(diamond).unwrap();
+        assert!(diamond.map_between("a", "d").is_err());
+        assert!(diamond.map_between("b", "d").is_ok());
+    }
+}
@@ -1,117 +1,262 @@
+#![allow(clippy::needless_return)]
+
+mod almanac;
+mod mapped;
+mod range_map;
+mod range_set;
+mod validate;
+
+use almanac::{Almanac, Listing};
 use itertools::{self, Itertools};
+use mapped::MappedSet;
+use range_map::RangeMap;
+use range_set::RangeSet;
 use std::ops::Range;
+use std::path::PathBuf;
 
-fn solve(input: &str) -> u64 {
-    let seeds_string = input.lines().next().unwrap().trim();
-    assert!(seeds_string.starts_with("seeds: "));
-    let mut source_set: Vec<u64> = seeds_string["seeds: ".len()..]
-        .split(" ")
-        .map(|it| it.parse().unwrap())
-        .collect();
-    let mut destination_set: Vec<u64> = Vec::new();
-
-    for line in input.lines().skip(1) {
-        if line.trim().is_empty() {
-            continue;
-        }
-        if line.contains(":") {
-            source_set.append(&mut destination_set);
-        } else {
-            let mapping: Vec<u64> = line
-                .trim()
-                .split(" ")
-                .map(|it| it.parse().unwrap())
-                .collect();
-            let dst_base = mapping[0];
-            let src_base = mapping[1];
-            let map_count = mapping[2];
-            let map = src_base..(src_base + map_count);
-
-            source_set.retain(|src| {
-                if map.contains(src) {
-                    destination_set.push(dst_base + src - src_base);
-                    false
-                } else {
-                    true
-                }
-            });
-        }
+fn seed_ranges(almanac: &Almanac) -> RangeSet {
+    return RangeSet::new(
+        almanac
+            .seeds
+            .iter()
+            .tuples()
+            .map(|(offset, count)| *offset..offset.saturating_add(*count)),
+    );
+}
+
+/// Whether a seed range includes u64::MAX, which a `RangeSet` cannot hold.
+fn reaches_largest(almanac: &Almanac) -> bool {
+    return almanac
+        .seeds
+        .iter()
+        .tuples()
+        .any(|(offset, count)| *count > u64::MAX - offset);
+}
+
+/// Where the seeds end up, with `largest` saying whether u64::MAX is one of them.
+fn locations(almanac: &Almanac, seeds: &RangeSet, largest: bool) -> Result<MappedSet, String> {
+    let chain = almanac.map_between("seed", "location")?;
+    return Ok(MappedSet::new(seeds, largest, &chain));
+}
+
+fn solve(input: &str) -> Result<u64, String> {
+    let almanac = Almanac::parse(input)?;
+    let seeds = RangeSet::new(
+        almanac
+            .seeds
+            .iter()
+            .filter_map(|it| Some(*it..it.checked_add(1)?)),
+    );
+    let largest = almanac.seeds.contains(&u64::MAX);
+    return locations(&almanac, &seeds, largest)?
+        .min()
+        .ok_or_else(|| "no seeds".to_string());
+}
+
+fn solve2(input: &str) -> Result<u64, String> {
+    let almanac = Almanac::parse(input)?;
+    let locations = locations(&almanac, &seed_ranges(&almanac), reaches_largest(&almanac))?;
+    return locations.min().ok_or_else(|| "no seeds".to_string());
+}
+
+fn print_locations(locations: &MappedSet, smallest: usize) {
+    match locations.max() {
+        Some(max) => println!(
+            "{} distinct locations, highest {max}",
+            locations.distinct_count()
+        ),
+        None => println!("no locations"),
+    }
+    for origin in locations.origins() {
+        println!(
+            "location {}..={} <- seed {}..={}",
+            origin.destination.start(),
+            origin.destination.end(),
+            origin.source.start(),
+            origin.source.end()
+        );
     }
+    if smallest > 0 {
+        let values: Vec<String> = locations
+            .smallest(smallest)
+            .iter()
+            .map(|it| it.to_string())
+            .collect();
+        println!("smallest: {}", values.join(" "));
+    }
+}
 
-    source_set.append(&mut destination_set);
+/// Prints every piece of the map, including the ones that map values to themselves.
+fn print_table(map: &RangeMap) {
+    println!(
+        "{:>20} {:>20} {:>20} {:>21}",
+        "from", "to", "maps to", "shift"
+    );
+    for piece in map.pieces(0..u64::MAX) {
+        let shift = piece.destination as i128 - piece.source.start as i128;
+        println!(
+            "{:>20} {:>20} {:>20} {:>+21}",
+            piece.source.start,
+            piece.source.end - 1,
+            piece.destination,
+            shift
+        );
+    }
+}
 
-    return *source_set.iter().min().unwrap();
+struct Args {
+    input: PathBuf,
+    from: String,
+    to: String,
+    table: bool,
+    lookups: Vec<u64>,
+    preimages: Vec<Range<u64>>,
+    validate: bool,
+    locations: bool,
+    smallest: usize,
 }
 
-fn solve2(input: &str) -> u64 {
-    let seeds_string = input.lines().next().unwrap().trim();
-    assert!(seeds_string.starts_with("seeds: "));
-    let mut source_set: Vec<Range<u64>> = seeds_string["seeds: ".len()..]
-        .split(" ")
-        .map(|it| it.parse().unwrap())
-        .tuples()
-        .map(|(offset, count)| offset..(offset + count))
-        .collect();
-    let mut destination_set: Vec<Range<u64>> = Vec::new();
+const USAGE: &str =
+    "usage: day05 [INPUT] [--from CATEGORY] [--to CATEGORY] [--table] [--lookup VALUE]...
+             [--preimage START END]... [--validate] [--locations] [--smallest K]";
 
-    for line in input.lines().skip(1) {
-        if line.trim().is_empty() {
-            continue;
-        }
-        if line.contains(":") {
-            source_set.append(&mut destination_set);
-        } else {
-            let mapping: Vec<u64> = line
-                .trim()
-                .split(" ")
-                .map(|it| it.parse().unwrap())
-                .collect();
-            let dst_base = mapping[0];
-            let src_base = mapping[1];
-            let map_count = mapping[2];
-            let map = src_base..(src_base + map_count);
-
-            for i in (0..source_set.len()).rev() {
-                let src = source_set[i].clone();
-                let intersect_start = src.start.max(map.start);
-                let intersect_end = src.end.min(map.end);
-                if intersect_end <= intersect_start {
-                    continue;
-                }
-
-                source_set.remove(i);
-
-                let left = src.start..intersect_start;
-                let intersect = intersect_start..intersect_end;
-                let right = intersect_end..src.end;
-
-                assert!(!intersect.is_empty());
-                let mapped_start = dst_base + intersect_start - src_base;
-                let mapped_count = intersect.end - intersect.start;
-                destination_set.push(mapped_start..(mapped_start + mapped_count));
-
-                if !left.is_empty() {
-                    source_set.push(left);
-                }
-                if !right.is_empty() {
-                    source_set.push(right);
-                }
+fn parse_args(args: impl Iterator<Item = String>) -> Result<Args, String> {
+    let mut input = None;
+    let mut from = "seed".to_string();
+    let mut to = "location".to_string();
+    let mut table = false;
+    let mut lookups = Vec::new();
+    let mut preimages = Vec::new();
+    let mut validate = false;
+    let mut locations = false;
+    let mut smallest = 0;
+    let mut args = args;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--from" => from = args.next().ok_or("--from needs a category")?,
+            "--to" => to = args.next().ok_or("--to needs a category")?,
+            "--table" => table = true,
+            "--lookup" => {
+                let value = args.next().ok_or("--lookup needs a value")?;
+                lookups.push(
+                    value
+                        .parse()
+                        .map_err(|err| format!("invalid value '{value}': {err}"))?,
+                );
             }
+            "--preimage" => {
+                let mut bound = || -> Result<u64, String> {
+                    let value = args.next().ok_or("--preimage needs a start and an end")?;
+                    return value
+                        .parse()
+                        .map_err(|err| format!("invalid value '{value}': {err}"));
+                };
+                preimages.push(bound()?..bound()?);
+            }
+            "--validate" => validate = true,
+            "--locations" => locations = true,
+            "--smallest" => {
+                let value = args.next().ok_or("--smallest needs a count")?;
+                smallest = value
+                    .parse()
+                    .map_err(|err| format!("invalid count '{value}': {err}"))?;
+            }
+            _ if arg.starts_with("--") => return Err(format!("unknown option '{arg}'")),
+            _ if input.is_none() => input = Some(PathBuf::from(arg)),
+            _ => return Err(format!("unexpected argument '{arg}'")),
         }
     }
-
-    source_set.append(&mut destination_set);
-
-    return source_set.iter().map(|r| r.start).min().unwrap();
+    return Ok(Args {
+        input: input.unwrap_or_else(|| PathBuf::from("input.txt")),
+        from,
+        to,
+        table,
+        lookups,
+        preimages,
+        validate,
+        locations,
+        smallest,
+    });
 }
 
 fn main() {
-    let input = std::fs::read_to_string("input.txt").unwrap();
-    let output = solve(input.as_str());
+    let args = parse_args(std::env::args().skip(1)).unwrap_or_else(|err| {
+        eprintln!("{err}\n{USAGE}");
+        std::process::exit(2);
+    });
+    let input = std::fs::read_to_string(&args.input).unwrap();
+    if args.validate {
+        let issues = validate::validate(&Listing::read(input.as_str()));
+        for issue in issues.iter() {
+            eprintln!("{issue}");
+        }
+        if !issues.is_empty() {
+            std::process::exit(1);
+        }
+    }
+
+    let fail = |err: String| -> u64 {
+        eprintln!("{err}");
+        if !args.validate {
+            eprintln!("run with --validate to list every problem with the maps");
+        }
+        std::process::exit(1);
+    };
+    let output = solve(input.as_str()).unwrap_or_else(fail);
     println!("{output}");
 
-    let output = solve2(input.as_str());
+    let output = solve2(input.as_str()).unwrap_or_else(fail);
     println!("{output}");
+
+    // Both parts have parsed the almanac and found the locations by now.
+    let almanac = Almanac::parse(input.as_str()).unwrap();
+    if args.locations || args.smallest > 0 {
+        let largest = reaches_largest(&almanac);
+        let locations = locations(&almanac, &seed_ranges(&almanac), largest).unwrap();
+        print_locations(&locations, args.smallest);
+    }
+
+    if args.table || !args.lookups.is_empty() || !args.preimages.is_empty() {
+        let map = almanac
+            .map_between(&args.from, &args.to)
+            .unwrap_or_else(|err| {
+                eprintln!("{err}");
+                std::process::exit(1);
+            });
+        for value in args.lookups.iter() {
+            println!("{} {value} -> {} {}", args.from, args.to, map.apply(*value));
+        }
+        let mut broken = false;
+        for target in args.preimages.iter() {
+            if !map.round_trips(target.clone()) {
+                eprintln!(
+                    "{} {}..{}: the {} values found do not map back onto it",
+                    args.to, target.start, target.end, args.from
+                );
+                broken = true;
+            }
+            let ranges: Vec<String> = map
+                .preimage(target.clone())
+                .ranges()
+                .iter()
+                .map(|it| format!("{}..{}", it.start, it.end))
+                .collect();
+            println!(
+                "{} {}..{} <- {} {}",
+                args.to,
+                target.start,
+                target.end,
+                args.from,
+                ranges.join(", ")
+            );
+        }
+        if args.table {
+            print_table(&map);
+        }
+        if broken {
+            std::process::exit(1);
+        }
+    }
 }
 
 #[cfg(test)]
@@ -157,7 +302,7 @@ mod tests {
     fn sample() {
         let expected_output = 35;
 
-        let output = solve(TEST_INPUT);
+        let output = solve(TEST_INPUT).unwrap();
 
         assert_eq!(expected_output, output);
     }
@@ -166,8 +311,112 @@ mod tests {
     fn sample2() {
         let expected_output = 46;
 
-        let output = solve2(TEST_INPUT);
+        let output = solve2(TEST_INPUT).unwrap();
 
         assert_eq!(expected_output, output);
     }
+
+    #[test]
+    fn chain() {
+        let almanac = Almanac::parse(TEST_INPUT).unwrap();
+
+        let chain = almanac.map_between("seed", "location").unwrap();
+
+        for seed in 0..120 {
+            let location = almanac
+                .conversions
+                .iter()
+                .fold(seed, |value, it| it.map.apply(value));
+            assert_eq!(location, chain.apply(seed));
+        }
+        assert_eq!(82, chain.apply(79));
+        assert_eq!(
+            RangeSet::new([46..56, 60..61]),
+            chain.apply_set(&RangeSet::from(82..93))
+        );
+    }
+
+    #[test]
+    fn seeds_for_locations() {
+        let chain = Almanac::parse(TEST_INPUT)
+            .unwrap()
+            .map_between("seed", "location")
+            .unwrap();
+
+        let seeds = chain.preimage(46..47);
+
+        assert_eq!(vec![82..83], seeds.ranges());
+        assert_eq!(vec![46..47], chain.apply_set(&seeds).ranges());
+        assert!(chain.round_trips(0..100));
+    }
+
+    #[test]
+    fn overlapping_maps() {
+        // The first line leaves 50..60 alone, which still rules out mapping 55..65.
+        let input = "seeds: 56
+
+                     seed-to-location map:
+                     50 50 10
+                     0 55 10";
+
+        assert!(solve(input).is_err());
+        assert!(solve2(input).is_err());
+        assert!(solve("seeds: 1\n\nseed-to-soil map:\n0 1 1").is_err());
+    }
+
+    #[test]
+    fn largest_seed() {
+        let input = "seeds: 18446744073709551615 10
+
+                     seed-to-location map:
+                     0 18446744073709551610 5";
+
+        assert_eq!(Ok(10), solve(input));
+        let only_largest = input.replace(" 10\n", "\n");
+        assert_eq!(Ok(u64::MAX), solve(&only_largest));
+        // Ten seeds from u64::MAX on run past the largest value, which leaves only that.
+        assert_eq!(Ok(u64::MAX), solve2(input));
+        let input = input.replace("18446744073709551615 10", "18446744073709551605 11");
+        assert_eq!(Ok(0), solve2(&input));
+
+        // The seed range holding just u64::MAX.
+        let almanac = Almanac::parse(&only_largest.replace("551615\n", "551615 1\n")).unwrap();
+        assert!(reaches_largest(&almanac));
+        let locations = locations(&almanac, &seed_ranges(&almanac), true).unwrap();
+        assert_eq!(Some(u64::MAX), locations.max());
+        assert_eq!(1, locations.distinct_count());
+        assert_eq!(vec![u64::MAX], locations.smallest(2));
+    }
+
+    #[test]
+    fn fragments_merge_again() {
+        // Swaps two halves, cutting every range that spans the middle in two.
+        let swap = "20 0 20\n0 20 20";
+        let mut input = "seeds: 5 30\n".to_string();
+        for index in 0..300 {
+            input += &format!("\nc{index}-to-c{} map:\n{swap}\n", index + 1);
+        }
+        let almanac = Almanac::parse(&input).unwrap();
+
+        let mut seeds = RangeSet::from(5..35);
+        for conversion in almanac.conversions.iter() {
+            seeds = conversion.map.apply_set(&seeds);
+            assert!(seeds.ranges().len() <= 2);
+        }
+
+        assert_eq!(vec![5..35], seeds.ranges());
+    }
+
+    #[test]
+    fn location_queries() {
+        let almanac = Almanac::parse(TEST_INPUT).unwrap();
+
+        let locations = locations(&almanac, &seed_ranges(&almanac), false).unwrap();
+
+        assert_eq!(Some(46), locations.min());
+        // The sample maps are one-to-one, so no two seeds share a location.
+        assert_eq!(14 + 13, locations.distinct_count());
+        assert_eq!(vec![46, 47, 48], locations.smallest(3));
+        assert_eq!(82, *locations.origins()[0].source.start());
+    }
 }
@@ -0,0 +1,176 @@
+use std::ops::RangeInclusive;
+
+use crate::range_map::RangeMap;
+use crate::range_set::RangeSet;
+
+/// Values that came out of a map, together with the values that went in. Inclusive, so
+/// that u64::MAX fits.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Origin {
+    pub destination: RangeInclusive<u64>,
+    pub source: RangeInclusive<u64>,
+}
+
+/// The result of pushing a set of values through a map, e.g. seeds to locations.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct MappedSet {
+    /// Sorted by destination. Destinations of different origins may overlap when the map
+    /// is not one-to-one.
+    origins: Vec<Origin>,
+    /// Everything that came out except u64::MAX.
+    destinations: RangeSet,
+    /// Whether u64::MAX came out.
+    largest: bool,
+}
+
+impl MappedSet {
+    /// `largest` says whether u64::MAX, which a `RangeSet` cannot hold, goes in along
+    /// with `set`. Maps always leave it in place.
+    pub fn new(set: &RangeSet, largest: bool, map: &RangeMap) -> MappedSet {
+        let pieces = set.split(map);
+        let destinations = RangeSet::new(pieces.iter().map(|it| it.destination_range()));
+        let mut origins: Vec<Origin> = pieces
+            .iter()
+            .map(|it| {
+                let destination = it.destination_range();
+                return Origin {
+                    destination: destination.start..=destination.end - 1,
+                    source: it.source.start..=it.source.end - 1,
+                };
+            })
+            .collect();
+        if largest {
+            origins.push(Origin {
+                destination: u64::MAX..=u64::MAX,
+                source: u64::MAX..=u64::MAX,
+            });
+        }
+        origins.sort_by_key(|it| (*it.destination.start(), *it.source.start()));
+        return MappedSet {
+            origins,
+            destinations,
+            largest,
+        };
+    }
+
+    pub fn min(&self) -> Option<u64> {
+        let largest = self.largest.then_some(u64::MAX);
+        return self
+            .destinations
+            .ranges()
+            .first()
+            .map(|it| it.start)
+            .or(largest);
+    }
+
+    pub fn max(&self) -> Option<u64> {
+        if self.largest {
+            return Some(u64::MAX);
+        }
+        return self.destinations.ranges().last().map(|it| it.end - 1);
+    }
+
+    pub fn origins(&self) -> &[Origin] {
+        return &self.origins;
+    }
+
+    /// How many different values came out, counting values reached twice once.
+    /// Wider than u64, since every value can come out.
+    pub fn distinct_count(&self) -> u128 {
+        let count: u128 = self
+            .destinations
+            .ranges()
+            .iter()
+            .map(|it| u128::from(it.end - it.start))
+            .sum();
+        return count + u128::from(self.largest);
+    }
+
+    /// The `k` smallest values that came out, without repeats.
+    pub fn smallest(&self, k: usize) -> Vec<u64> {
+        return self
+            .destinations
+            .ranges()
+            .iter()
+            .flat_map(|it| it.clone())
+            .chain(self.largest.then_some(u64::MAX))
+            .take(k)
+            .collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::range_map::Segment;
+
+    #[test]
+    fn queries() {
+        // 0..5 and 20..25 both end up at 10..15.
+        let map = RangeMap::new(vec![
+            Segment::parse("10 0 5").unwrap(),
+            Segment::parse("10 20 5").unwrap(),
+            Segment::parse("100 30 2").unwrap(),
+        ])
+        .unwrap();
+        let seeds = RangeSet::new([0..8, 20..22, 31..40]);
+
+        let mapped = MappedSet::new(&seeds, false, &map);
+
+        assert_eq!(Some(5), mapped.min());
+        assert_eq!(Some(101), mapped.max());
+        assert_eq!(vec![5, 6, 7, 10, 11, 12], mapped.smallest(6));
+        // 5..8, 10..15, 32..40 and 101.
+        assert_eq!(3 + 5 + 8 + 1, mapped.distinct_count());
+        let origins: Vec<(RangeInclusive<u64>, RangeInclusive<u64>)> = mapped
+            .origins()
+            .iter()
+            .map(|it| (it.destination.clone(), it.source.clone()))
+            .collect();
+        assert_eq!(
+            vec![
+                (5..=7, 5..=7),
+                (10..=14, 0..=4),
+                (10..=11, 20..=21),
+                (32..=39, 32..=39),
+                (101..=101, 31..=31),
+            ],
+            origins
+        );
+    }
+
+    #[test]
+    fn empty() {
+        let mapped = MappedSet::new(&RangeSet::default(), false, &RangeMap::default());
+
+        assert_eq!(None, mapped.min());
+        assert_eq!(None, mapped.max());
+        assert_eq!(0, mapped.distinct_count());
+        assert!(mapped.smallest(3).is_empty());
+        assert!(mapped.origins().is_empty());
+    }
+
+    #[test]
+    fn largest() {
+        let map = RangeMap::new(vec![Segment::parse("0 10 5").unwrap()]).unwrap();
+
+        let only = MappedSet::new(&RangeSet::default(), true, &map);
+
+        assert_eq!(Some(u64::MAX), only.min());
+        assert_eq!(Some(u64::MAX), only.max());
+        assert_eq!(1, only.distinct_count());
+        assert_eq!(vec![u64::MAX], only.smallest(3));
+        let origin = Origin {
+            destination: u64::MAX..=u64::MAX,
+            source: u64::MAX..=u64::MAX,
+        };
+        assert_eq!(&[origin], only.origins());
+
+        let everything = MappedSet::new(&RangeSet::from(0..u64::MAX), true, &RangeMap::default());
+
+        assert_eq!(Some(0), everything.min());
+        assert_eq!(Some(u64::MAX), everything.max());
+        assert_eq!(1 << 64, everything.distinct_count());
+        assert_eq!(vec![0, 1], everything.smallest(2));
+    }
+}
@@ -0,0 +1,350 @@
+use std::ops::Range;
+
+use crate::range_set::RangeSet;
+
+/// An almanac line as written, `DESTINATION SOURCE LENGTH`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Entry {
+    pub destination: u64,
+    pub source: u64,
+    pub length: u64,
+}
+
+impl Entry {
+    pub fn parse(line: &str) -> Result<Entry, String> {
+        let numbers: Vec<u64> = line
+            .split_whitespace()
+            .map(|it| {
+                it.parse()
+                    .map_err(|err| format!("invalid number '{it}': {err}"))
+            })
+            .collect::<Result<_, String>>()?;
+        let [destination, source, length] = numbers[..] else {
+            return Err(format!("expected three numbers, got '{}'", line.trim()));
+        };
+        return Ok(Entry {
+            destination,
+            source,
+            length,
+        });
+    }
+
+    /// The segment, an error if it runs past the largest value.
+    pub fn segment(&self) -> Result<Segment, String> {
+        let end = self.source.checked_add(self.length);
+        let (Some(end), Some(_)) = (end, self.destination.checked_add(self.length)) else {
+            return Err(format!(
+                "'{} {} {}' runs past the largest value",
+                self.destination, self.source, self.length
+            ));
+        };
+        return Ok(Segment {
+            source: self.source..end,
+            destination: self.destination,
+        });
+    }
+}
+
+/// Maps the values in `source` onto the same number of values starting at `destination`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Segment {
+    pub source: Range<u64>,
+    pub destination: u64,
+}
+
+impl Segment {
+    /// Reads an almanac line, see [`Entry`]. The almanac itself keeps the entries around
+    /// for validation, so only tests go straight to segments.
+    #[cfg(test)]
+    pub fn parse(line: &str) -> Result<Segment, String> {
+        return Entry::parse(line)?.segment();
+    }
+
+    fn map(&self, value: u64) -> u64 {
+        return self.destination + (value - self.source.start);
+    }
+
+    pub fn destination_range(&self) -> Range<u64> {
+        return self.destination..self.map(self.source.end);
+    }
+}
+
+/// A piecewise shift of `0..u64::MAX`, where values outside every segment map to
+/// themselves.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct RangeMap {
+    /// Sorted by source and never overlapping, empty or identity.
+    segments: Vec<Segment>,
+}
+
+impl RangeMap {
+    pub fn new(segments: Vec<Segment>) -> Result<RangeMap, String> {
+        // Identity segments still claim their sources, so they are only dropped by `push`
+        // after checking for overlaps.
+        let mut segments: Vec<Segment> = segments
+            .into_iter()
+            .filter(|it| !it.source.is_empty())
+            .collect();
+        segments.sort_by_key(|it| it.source.start);
+        for pair in segments.windows(2) {
+            if pair[1].source.start < pair[0].source.end {
+                return Err(format!(
+                    "sources {:?} and {:?} overlap",
+                    pair[0].source, pair[1].source
+                ));
+            }
+        }
+        let mut result = RangeMap {
+            segments: Vec::new(),
+        };
+        for segment in segments {
+            result.push(segment);
+        }
+        return Ok(result);
+    }
+
+    /// Appends a segment that starts at or after the end of the last one, merging the two
+    /// when they shift by the same amount.
+    fn push(&mut self, segment: Segment) {
+        if segment.source.is_empty() || segment.source.start == segment.destination {
+            return;
+        }
+        if let Some(last) = self.segments.last_mut() {
+            if last.source.end == segment.source.start
+                && last.map(last.source.end) == segment.destination
+            {
+                last.source.end = segment.source.end;
+                return;
+            }
+        }
+        self.segments.push(segment);
+    }
+
+    pub fn apply(&self, value: u64) -> u64 {
+        let index = self.segments.partition_point(|it| it.source.end <= value);
+        return match self.segments.get(index) {
+            Some(segment) if segment.source.contains(&value) => segment.map(value),
+            _ => value,
+        };
+    }
+
+    /// Splits `range` into the pieces that are shifted as one, each with where its first
+    /// value maps to. Identity pieces are included.
+    pub fn pieces(&self, range: Range<u64>) -> Vec<Segment> {
+        let mut result = Vec::new();
+        let mut start = range.start;
+        let mut index = self.segments.partition_point(|it| it.source.end <= start);
+        while start < range.end {
+            let Some(segment) = self.segments.get(index) else {
+                result.push(Segment {
+                    source: start..range.end,
+                    destination: start,
+                });
+                break;
+            };
+            if start < segment.source.start {
+                let end = segment.source.start.min(range.end);
+                result.push(Segment {
+                    source: start..end,
+                    destination: start,
+                });
+                start = end;
+                continue;
+            }
+            let end = segment.source.end.min(range.end);
+            result.push(Segment {
+                source: start..end,
+                destination: segment.map(start),
+            });
+            start = end;
+            index += 1;
+        }
+        return result;
+    }
+
+    /// The values the set maps to.
+    pub fn apply_set(&self, set: &RangeSet) -> RangeSet {
+        return RangeSet::new(set.split(self).iter().map(|it| it.destination_range()));
+    }
+
+    /// Every value that maps into `target`. Unlike `inverse` this also works when several
+    /// values map to the same one.
+    pub fn preimage(&self, target: Range<u64>) -> RangeSet {
+        let mut result = Vec::new();
+        for segment in self.segments.iter() {
+            let destination = segment.destination_range();
+            let start = destination.start.max(target.start);
+            let end = destination.end.min(target.end);
+            if start < end {
+                let source = segment.source.start + (start - destination.start);
+                result.push(source..source + (end - start));
+            }
+        }
+        // Values outside every segment reach themselves.
+        for piece in self.pieces(target) {
+            if piece.destination == piece.source.start {
+                result.push(piece.source);
+            }
+        }
+        return RangeSet::new(result);
+    }
+
+    /// Whether mapping the preimage of `target` forward again gives exactly the values in
+    /// `target` that can be reached at all.
+    pub fn round_trips(&self, target: Range<u64>) -> bool {
+        let reached = self.apply_set(&self.preimage(target.clone()));
+        let image = RangeSet::new(
+            self.pieces(0..u64::MAX)
+                .iter()
+                .map(|it| it.destination_range()),
+        );
+        return reached == image.intersection(&RangeSet::from(target));
+    }
+
+    /// The map that undoes this one, if every value has exactly one value mapping to it.
+    pub fn inverse(&self) -> Result<RangeMap, String> {
+        // The map is one-to-one exactly when the segments permute the values they cover.
+        let sources = RangeSet::new(self.segments.iter().map(|it| it.source.clone()));
+        let mut destinations: Vec<Range<u64>> = self
+            .segments
+            .iter()
+            .map(|it| it.destination_range())
+            .collect();
+        destinations.sort_by_key(|it| it.start);
+        for pair in destinations.windows(2) {
+            if pair[1].start < pair[0].end {
+                return Err(format!(
+                    "destinations {:?} and {:?} overlap",
+                    pair[0], pair[1]
+                ));
+            }
+        }
+        if sources != RangeSet::new(destinations) {
+            return Err("some values are reached both by a segment and unmapped".to_string());
+        }
+        return RangeMap::new(
+            self.segments
+                .iter()
+                .map(|it| Segment {
+                    source: it.destination_range(),
+                    destination: it.source.start,
+                })
+                .collect(),
+        );
+    }
+
+    /// The map that applies this one and then `then`.
+    pub fn compose(&self, then: &RangeMap) -> RangeMap {
+        let mut result = RangeMap::default();
+        for piece in self.pieces(0..u64::MAX) {
+            let image = piece.destination_range();
+            for next in then.pieces(image.clone()) {
+                let start = piece.source.start + (next.source.start - image.start);
+                let end = start + (next.source.end - next.source.start);
+                result.push(Segment {
+                    source: start..end,
+                    destination: next.destination,
+                });
+            }
+        }
+        return result;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map(lines: &[&str]) -> RangeMap {
+        return RangeMap::new(lines.iter().map(|it| Segment::parse(it).unwrap()).collect())
+            .unwrap();
+    }
+
+    #[test]
+    fn apply() {
+        let seed_to_soil = map(&["50 98 2", "52 50 48"]);
+
+        assert_eq!(81, seed_to_soil.apply(79));
+        assert_eq!(14, seed_to_soil.apply(14));
+        assert_eq!(51, seed_to_soil.apply(99));
+        assert_eq!(100, seed_to_soil.apply(100));
+        let seeds = RangeSet::new([0..10, 50..52, 96..110]);
+        assert_eq!(
+            RangeSet::new([0..10, 50..54, 98..110]),
+            seed_to_soil.apply_set(&seeds)
+        );
+    }
+
+    #[test]
+    fn invalid_segments() {
+        assert!(Segment::parse("1 2").is_err());
+        assert!(Segment::parse("1 2 x").is_err());
+        assert!(Segment::parse("0 18446744073709551615 2").is_err());
+        assert!(RangeMap::new(vec![
+            Segment::parse("0 10 5").unwrap(),
+            Segment::parse("20 14 5").unwrap(),
+        ])
+        .is_err());
+        assert!(RangeMap::new(vec![
+            Segment::parse("50 50 10").unwrap(),
+            Segment::parse("0 55 10").unwrap(),
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn inverse() {
+        let seed_to_soil = map(&["50 98 2", "52 50 48"]);
+
+        let soil_to_seed = seed_to_soil.inverse().unwrap();
+
+        for value in 0..200 {
+            assert_eq!(value, soil_to_seed.apply(seed_to_soil.apply(value)));
+        }
+        // Both 5 and 10 would map to 10.
+        assert!(map(&["10 5 1"]).inverse().is_err());
+        assert!(map(&["10 5 2", "11 20 1"]).inverse().is_err());
+    }
+
+    #[test]
+    fn preimage() {
+        // Both 10..12 and 30..32 reach 10..12, and nothing reaches 30..32.
+        let many_to_one = map(&["10 30 2", "100 0 5"]);
+        let chain = map(&["50 98 2", "52 50 48"]).compose(&many_to_one);
+
+        for target in [0..3, 10..12, 11..40, 50..60, 96..104, 0..200] {
+            let preimage = chain.preimage(target.clone());
+            for value in 0..300 {
+                assert_eq!(
+                    target.contains(&chain.apply(value)),
+                    preimage.contains(value)
+                );
+            }
+            assert!(chain.round_trips(target));
+        }
+        assert_eq!(vec![10..12, 30..32], many_to_one.preimage(10..12).ranges());
+        assert!(many_to_one.preimage(30..32).is_empty());
+        assert_eq!(
+            vec![0..2, 100..102],
+            many_to_one.preimage(100..102).ranges()
+        );
+    }
+
+    #[test]
+    fn compose() {
+        let seed_to_soil = map(&["50 98 2", "52 50 48"]);
+        let soil_to_fertilizer = map(&["0 15 37", "37 52 2", "39 0 15"]);
+
+        let seed_to_fertilizer = seed_to_soil.compose(&soil_to_fertilizer);
+
+        for value in 0..200 {
+            assert_eq!(
+                soil_to_fertilizer.apply(seed_to_soil.apply(value)),
+                seed_to_fertilizer.apply(value)
+            );
+        }
+        assert_eq!(seed_to_soil, seed_to_soil.compose(&RangeMap::default()));
+        let identity = seed_to_soil.compose(&seed_to_soil.inverse().unwrap());
+        assert_eq!(RangeMap::default(), identity);
+    }
+}
@@ -0,0 +1,161 @@
+use std::ops::Range;
+
+use crate::range_map::{RangeMap, Segment};
+
+/// A set of values stored as ranges.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct RangeSet {
+    /// Sorted, non-empty and neither overlapping nor touching.
+    ranges: Vec<Range<u64>>,
+}
+
+impl RangeSet {
+    pub fn new(ranges: impl IntoIterator<Item = Range<u64>>) -> RangeSet {
+        let mut ranges: Vec<Range<u64>> = ranges.into_iter().filter(|it| !it.is_empty()).collect();
+        ranges.sort_by_key(|it| it.start);
+        let mut result: Vec<Range<u64>> = Vec::new();
+        for range in ranges {
+            match result.last_mut() {
+                Some(last) if last.end >= range.start => last.end = last.end.max(range.end),
+                _ => result.push(range),
+            }
+        }
+        return RangeSet { ranges: result };
+    }
+
+    pub fn ranges(&self) -> &[Range<u64>] {
+        return &self.ranges;
+    }
+
+    #[allow(dead_code)]
+    pub fn is_empty(&self) -> bool {
+        return self.ranges.is_empty();
+    }
+
+    #[allow(dead_code)]
+    pub fn contains(&self, value: u64) -> bool {
+        let index = self.ranges.partition_point(|it| it.end <= value);
+        return self.ranges.get(index).is_some_and(|it| it.contains(&value));
+    }
+
+    #[allow(dead_code)]
+    pub fn union(&self, other: &RangeSet) -> RangeSet {
+        return RangeSet::new(self.ranges.iter().chain(other.ranges.iter()).cloned());
+    }
+
+    pub fn intersection(&self, other: &RangeSet) -> RangeSet {
+        let mut result = Vec::new();
+        let (mut i, mut j) = (0, 0);
+        while i < self.ranges.len() && j < other.ranges.len() {
+            let (a, b) = (&self.ranges[i], &other.ranges[j]);
+            let start = a.start.max(b.start);
+            let end = a.end.min(b.end);
+            if start < end {
+                result.push(start..end);
+            }
+            if a.end < b.end {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+        return RangeSet { ranges: result };
+    }
+
+    #[allow(dead_code)]
+    pub fn difference(&self, other: &RangeSet) -> RangeSet {
+        let mut result = Vec::new();
+        let mut j = 0;
+        for range in self.ranges.iter() {
+            let mut start = range.start;
+            while j < other.ranges.len() && other.ranges[j].end <= start {
+                j += 1;
+            }
+            let mut k = j;
+            while start < range.end {
+                let Some(cut) = other.ranges.get(k).filter(|it| it.start < range.end) else {
+                    result.push(start..range.end);
+                    break;
+                };
+                if start < cut.start {
+                    result.push(start..cut.start);
+                }
+                start = start.max(cut.end);
+                k += 1;
+            }
+        }
+        return RangeSet { ranges: result };
+    }
+
+    /// Cuts the set into the pieces the map shifts as one, in order.
+    pub fn split(&self, map: &RangeMap) -> Vec<Segment> {
+        return self
+            .ranges
+            .iter()
+            .flat_map(|it| map.pieces(it.clone()))
+            .collect();
+    }
+}
+
+impl From<Range<u64>> for RangeSet {
+    fn from(range: Range<u64>) -> RangeSet {
+        return RangeSet::new([range]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalized() {
+        let set = RangeSet::new([5..8, 0..2, 2..3, 7..10, 4..4, 12..13]);
+
+        assert_eq!(vec![0..3, 5..10, 12..13], set.ranges());
+        assert!(set.contains(2) && set.contains(9) && set.contains(12));
+        assert!(!set.contains(3) && !set.contains(10) && !set.contains(13));
+        assert!(RangeSet::from(4..4).is_empty());
+    }
+
+    #[test]
+    fn operations() {
+        let a = RangeSet::new([0..10, 20..30, 40..50]);
+        let b = RangeSet::new([5..25, 28..29, 45..60]);
+
+        assert_eq!(vec![0..30, 40..60], a.union(&b).ranges());
+        assert_eq!(
+            vec![5..10, 20..25, 28..29, 45..50],
+            a.intersection(&b).ranges()
+        );
+        assert_eq!(
+            vec![0..5, 25..28, 29..30, 40..45],
+            a.difference(&b).ranges()
+        );
+        assert_eq!(vec![10..20, 50..60], b.difference(&a).ranges());
+        assert_eq!(a, a.difference(&RangeSet::default()));
+        assert!(a.difference(&a).is_empty());
+
+        for value in 0..70 {
+            let (in_a, in_b) = (a.contains(value), b.contains(value));
+            assert_eq!(in_a || in_b, a.union(&b).contains(value));
+            assert_eq!(in_a && in_b, a.intersection(&b).contains(value));
+            assert_eq!(in_a && !in_b, a.difference(&b).contains(value));
+        }
+    }
+
+    #[test]
+    fn split() {
+        let map = RangeMap::new(vec![Segment::parse("50 98 2").unwrap()]).unwrap();
+        let set = RangeSet::new([90..100, 110..120]);
+
+        let pieces = set.split(&map);
+
+        assert_eq!(
+            vec![(90..98, 90), (98..100, 50), (110..120, 110)],
+            pieces
+                .into_iter()
+                .map(|it| (it.source, it.destination))
+                .collect::<Vec<(Range<u64>, u64)>>()
+        );
+    }
+}
@@ -0,0 +1,292 @@
+use std::fmt;
+use std::ops::Range;
+
+use crate::almanac::{Block, Listing};
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Problem {
+    /// Values in both sources would be mapped by whichever line comes first.
+    OverlappingSources {
+        other: usize,
+    },
+    /// Values from both lines end up in the same place, so the map is not one-to-one.
+    OverlappingDestinations {
+        other: usize,
+    },
+    /// The destination covers `value`, which no source includes and so also maps to
+    /// itself.
+    ReachesUnmapped {
+        value: u64,
+    },
+    ZeroLength,
+    /// The source or destination range runs past the largest u64.
+    Overflow,
+    /// The same `X-to-Y map:` header as on line `other`.
+    RepeatedMap {
+        other: usize,
+    },
+    Malformed(String),
+}
+
+impl Problem {
+    /// Whether the almanac cannot be used at all. The others are only suspicious.
+    pub fn is_fatal(&self) -> bool {
+        return !matches!(
+            self,
+            Problem::OverlappingDestinations { .. }
+                | Problem::ReachesUnmapped { .. }
+                | Problem::ZeroLength
+        );
+    }
+}
+
+/// A problem with the almanac on a 1-based `line`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Issue {
+    pub line: usize,
+    pub problem: Problem,
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: ", self.line)?;
+        return match &self.problem {
+            Problem::OverlappingSources { other } => {
+                write!(f, "source overlaps the one on line {other}")
+            }
+            Problem::OverlappingDestinations { other } => {
+                write!(f, "destination overlaps the one on line {other}")
+            }
+            Problem::ReachesUnmapped { value } => {
+                write!(
+                    f,
+                    "destination includes {value}, which the map leaves in place"
+                )
+            }
+            Problem::ZeroLength => write!(f, "range is empty"),
+            Problem::Overflow => write!(f, "range runs past {}", u64::MAX),
+            Problem::RepeatedMap { other } => write!(f, "map already listed on line {other}"),
+            Problem::Malformed(message) => write!(f, "{message}"),
+        };
+    }
+}
+
+fn overlaps(a: &Range<u64>, b: &Range<u64>) -> bool {
+    return a.start < b.end && b.start < a.end;
+}
+
+/// The first value of `destination` outside all of the `sources`, which are sorted by
+/// start.
+fn first_unmapped(destination: &Range<u64>, sources: &[Range<u64>]) -> Option<u64> {
+    let mut value = destination.start;
+    for source in sources {
+        if source.start > value {
+            break;
+        }
+        value = value.max(source.end);
+    }
+    return if value < destination.end {
+        Some(value)
+    } else {
+        None
+    };
+}
+
+fn check_map(block: &Block, issues: &mut Vec<Issue>) {
+    let mut ranges: Vec<(usize, Range<u64>, Range<u64>)> = Vec::new();
+    for (line, entry) in block.entries.iter() {
+        if entry.length == 0 {
+            issues.push(Issue {
+                line: *line,
+                problem: Problem::ZeroLength,
+            });
+            continue;
+        }
+        let Ok(segment) = entry.segment() else {
+            issues.push(Issue {
+                line: *line,
+                problem: Problem::Overflow,
+            });
+            continue;
+        };
+        ranges.push((*line, segment.destination_range(), segment.source));
+    }
+    let mut sources: Vec<Range<u64>> = ranges.iter().map(|it| it.2.clone()).collect();
+    sources.sort_by_key(|it| it.start);
+    for (index, (line, destination, source)) in ranges.iter().enumerate() {
+        for (other, other_destination, other_source) in ranges[..index].iter() {
+            if overlaps(source, other_source) {
+                issues.push(Issue {
+                    line: *line,
+                    problem: Problem::OverlappingSources { other: *other },
+                });
+            }
+            if overlaps(destination, other_destination) {
+                issues.push(Issue {
+                    line: *line,
+                    problem: Problem::OverlappingDestinations { other: *other },
+                });
+            }
+        }
+        if let Some(value) = first_unmapped(destination, &sources) {
+            issues.push(Issue {
+                line: *line,
+                problem: Problem::ReachesUnmapped { value },
+            });
+        }
+    }
+}
+
+/// Every problem with the listing, ordered by line. Only entries within the same map
+/// are compared.
+pub fn validate(listing: &Listing) -> Vec<Issue> {
+    let mut issues: Vec<Issue> = listing
+        .errors
+        .iter()
+        .map(|(line, message)| Issue {
+            line: *line,
+            problem: Problem::Malformed(message.clone()),
+        })
+        .collect();
+    for (index, block) in listing.blocks.iter().enumerate() {
+        let earlier = listing.blocks[..index]
+            .iter()
+            .find(|it| it.from == block.from && it.to == block.to);
+        if let Some(earlier) = earlier {
+            issues.push(Issue {
+                line: block.line,
+                problem: Problem::RepeatedMap {
+                    other: earlier.line,
+                },
+            });
+        }
+        check_map(block, &mut issues);
+    }
+    issues.sort_by_key(|it| it.line);
+    return issues;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn problems() {
+        let input = "seeds: 1 2
+                     a-to-b map:
+                     50 98 2
+                     52 50 48
+                     0 99 10
+                     60 0 5
+                     0 200 0
+
+                     b-to-c map:
+                     0 18446744073709551610 10
+                     18446744073709551610 0 10
+                     0 1 2 3
+                     0 99 10
+
+                     a-to-b map:
+                     1 2 3
+                     c to d map:
+                     1 2 3";
+
+        let issues = validate(&Listing::read(input));
+
+        let expected = vec![
+            Issue {
+                line: 5,
+                problem: Problem::OverlappingSources { other: 3 },
+            },
+            Issue {
+                line: 5,
+                problem: Problem::ReachesUnmapped { value: 5 },
+            },
+            Issue {
+                line: 6,
+                problem: Problem::OverlappingDestinations { other: 4 },
+            },
+            Issue {
+                line: 7,
+                problem: Problem::ZeroLength,
+            },
+            Issue {
+                line: 10,
+                problem: Problem::Overflow,
+            },
+            Issue {
+                line: 11,
+                problem: Problem::Overflow,
+            },
+            Issue {
+                line: 12,
+                problem: Problem::Malformed("expected three numbers, got '0 1 2 3'".to_string()),
+            },
+            Issue {
+                line: 13,
+                problem: Problem::ReachesUnmapped { value: 0 },
+            },
+            Issue {
+                line: 15,
+                problem: Problem::RepeatedMap { other: 2 },
+            },
+            Issue {
+                line: 16,
+                problem: Problem::ReachesUnmapped { value: 1 },
+            },
+            Issue {
+                line: 17,
+                problem: Problem::Malformed(
+                    "expected 'X-to-Y map:', got 'c to d map:'".to_string(),
+                ),
+            },
+        ];
+        assert_eq!(expected, issues);
+        assert_eq!(
+            "line 5: source overlaps the one on line 3",
+            issues[0].to_string()
+        );
+    }
+
+    #[test]
+    fn reaches_unmapped() {
+        let input = "seeds: 5 10
+                     seed-to-location map:
+                     10 5 1";
+
+        let issues = validate(&Listing::read(input));
+
+        assert_eq!(
+            vec![Issue {
+                line: 3,
+                problem: Problem::ReachesUnmapped { value: 10 },
+            }],
+            issues
+        );
+        assert_eq!(
+            "line 3: destination includes 10, which the map leaves in place",
+            issues[0].to_string()
+        );
+        // Swapping the two values is fine.
+        let input = "seeds: 5 10
+                     seed-to-location map:
+                     10 5 1
+                     5 10 1";
+        assert!(validate(&Listing::read(input)).is_empty());
+    }
+
+    #[test]
+    fn sample_is_valid() {
+        let input = "seeds: 79 14 55 13
+                     seed-to-soil map:
+                     50 98 2
+                     52 50 48
+
+                     soil-to-fertilizer map:
+                     0 15 37
+                     37 52 2
+                     39 0 15";
+
+        assert!(validate(&Listing::read(input)).is_empty());
+    }
+}
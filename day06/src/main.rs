@@ -1,3 +1,5 @@
+#![allow(clippy::needless_return)]
+
 use std::ops::Range;
 
 struct Race {
@@ -5,49 +7,63 @@ struct Race {
     distance: u64,
 }
 
+impl Race {
+    /// Whether charging for `charge` milliseconds beats the record.
+    fn wins(&self, charge: u64) -> bool {
+        if charge > self.duration {
+            return false;
+        }
+        let travelled = charge as u128 * (self.duration - charge) as u128;
+        return travelled > self.distance as u128;
+    }
+}
+
 fn find_winning_interval(race: &Race) -> Option<Range<u64>> {
     // t = t_charge + t_move
     // v = t_charge
     // d = v * t_move
     // 0 = (t - t_move) * t_move - d
     // 0 = -t_move*t_move + t*t_move - d
-    // x1/2 = (-b +/- sqrt(b*b - 4ac)) / 2a
-    let a: f64 = -1.0;
-    let b = race.duration as f64;
-    let c = -(race.distance as f64);
-
-    let d = b * b - 4.0 * a * c;
-    if d < 0.0 {
+    // x1/2 = (t +/- sqrt(t*t - 4d)) / 2
+    // Everything fits in a u128, so the roots are exact up to the rounding of the
+    // integer square root, which the checks below correct.
+    let t = race.duration as u128;
+    let d = race.distance as u128;
+    if t * t < 4 * d {
+        return None;
+    }
+    let sqrtd = (t * t - 4 * d).isqrt();
+    let mut w1 = ((t - sqrtd) / 2) as u64;
+    let mut w2 = ((t + sqrtd) / 2) as u64;
+
+    // We want to be better than the current best, and the square root
+    // is rounded down, so move both ends to the outermost charges that win.
+    while w1 <= w2 && !race.wins(w1) {
+        w1 += 1;
+    }
+    while w2 >= w1 && !race.wins(w2) {
+        w2 -= 1;
+    }
+    if w1 > w2 {
         return None;
     }
-    let sqrtd = d.sqrt();
-    let x1 = (b - sqrtd) / 2.0;
-    let x2 = (b + sqrtd) / 2.0;
-
-    // We want to be better than the current best, so in case we hit
-    // it exactly, step one up / down.
-    let w1 = if x1.fract() == 0.0 {
-        x1 as u64 + 1
-    } else {
-        x1.ceil() as u64
-    };
-    let w2 = if x2.fract() == 0.0 {
-        x2 as u64 - 1
-    } else {
-        x2.floor() as u64
-    };
+    while w1 > 0 && race.wins(w1 - 1) {
+        w1 -= 1;
+    }
+    while w2 < race.duration && race.wins(w2 + 1) {
+        w2 += 1;
+    }
+    debug_assert!(race.wins(w1) && race.wins(w2));
     return Some(w1..(w2 + 1));
 }
 
 fn parse_input(input: &str) -> Vec<Race> {
     let mut lines = input.lines();
     let times: Vec<u64> = lines.next().unwrap().trim()["Time:".len()..]
-        .trim()
         .split_whitespace()
         .map(|it| it.parse().unwrap())
         .collect();
     let distances: Vec<u64> = lines.next().unwrap().trim()["Distance:".len()..]
-        .trim()
         .split_whitespace()
         .map(|it| it.parse().unwrap())
         .collect();
@@ -124,4 +140,42 @@ mod tests {
 
         assert_eq!(expected_output, output);
     }
+
+    #[test]
+    fn matches_brute_force() {
+        for duration in 0..60 {
+            for distance in 0..(duration * duration / 4 + 3) {
+                let race = Race { duration, distance };
+                let winning: Vec<u64> = (0..=duration).filter(|it| race.wins(*it)).collect();
+
+                let expected = match (winning.first(), winning.last()) {
+                    (Some(first), Some(last)) => Some(*first..(last + 1)),
+                    _ => None,
+                };
+
+                assert_eq!(
+                    expected,
+                    find_winning_interval(&race),
+                    "{duration} {distance}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn exact_for_large_races() {
+        // The record is hit exactly by charging 123 ms, beyond what an f64 can resolve.
+        let duration = (1 << 54) + 7;
+        let distance = 123 * (duration - 123);
+        let race = Race { duration, distance };
+
+        assert_eq!(Some(124..(duration - 123)), find_winning_interval(&race));
+        let race = Race {
+            duration: u64::MAX,
+            distance: u64::MAX,
+        };
+        let interval = find_winning_interval(&race).unwrap();
+        assert!(!race.wins(interval.start - 1) && race.wins(interval.start));
+        assert!(race.wins(interval.end - 1) && !race.wins(interval.end));
+    }
 }